@@ -0,0 +1,479 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+
+/// Sign-magnitude integer with little-endian base 2^32 limbs. The magnitude never has
+/// trailing zero limbs, and zero is always non-negative, so derived equality is exact.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn from_i64(value: i64) -> BigInt {
+        let magnitude = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+
+    /// Exact conversion of an integral float; `None` for fractions, infinities and NaN.
+    pub fn from_f64(value: f64) -> Option<BigInt> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        if exponent == 0 {
+            return Some(BigInt::default());
+        }
+
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let shift = exponent - 1075;
+        let magnitude = if shift >= 0 {
+            BigInt::from_i64(mantissa as i64).shl(shift as usize)
+        } else {
+            BigInt::from_i64((mantissa >> -shift) as i64)
+        };
+        Some(if value < 0.0 { magnitude.neg() } else { magnitude })
+    }
+
+    pub fn parse(digits: &str, radix: u32) -> Option<BigInt> {
+        let mut magnitude = Vec::new();
+
+        for c in digits.chars() {
+            let digit = c.to_digit(radix)?;
+            let mut carry = digit as u64;
+            for limb in magnitude.iter_mut() {
+                let product = *limb as u64 * radix as u64 + carry;
+                *limb = product as u32;
+                carry = product >> 32;
+            }
+            if carry != 0 {
+                magnitude.push(carry as u32);
+            }
+        }
+
+        Some(BigInt::from_parts(false, magnitude))
+    }
+
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self.magnitude.iter().rev().fold(0u64, |acc, &limb| (acc << 32) | limb as u64);
+        if self.negative {
+            match magnitude.cmp(&(1u64 << 63)) {
+                Ordering::Less => Some(-(magnitude as i64)),
+                Ordering::Equal => Some(i64::MIN),
+                Ordering::Greater => None,
+            }
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.magnitude.iter().rev().fold(0.0, |acc, &limb| acc * 4294967296.0 + limb as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn neg(&self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitudes(&self.magnitude, &other.magnitude));
+        }
+
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_magnitudes(&other.magnitude, &self.magnitude)),
+            _ => BigInt::from_parts(self.negative, sub_magnitudes(&self.magnitude, &other.magnitude)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        let mut product = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+
+        for (i, &a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.magnitude.iter().enumerate() {
+                let sum = product[i + j] as u64 + a as u64 * b as u64 + carry;
+                product[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+
+        BigInt::from_parts(self.negative != other.negative, product)
+    }
+
+    /// Truncating division, matching `i64` semantics: the quotient rounds toward zero and
+    /// the remainder takes the sign of the dividend. Returns `None` when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitudes(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    pub fn div(&self, other: &BigInt) -> BigInt {
+        self.div_rem(other).map(|(quotient, _)| quotient).unwrap_or_default()
+    }
+
+    pub fn rem(&self, other: &BigInt) -> BigInt {
+        self.div_rem(other).map(|(_, remainder)| remainder).unwrap_or_default()
+    }
+
+    pub fn bit_and(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a & b)
+    }
+
+    pub fn bit_or(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a | b)
+    }
+
+    pub fn bit_xor(&self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+
+    pub fn bit_not(&self) -> BigInt {
+        self.neg().sub(&BigInt::from_i64(1))
+    }
+
+    pub fn shl(&self, amount: usize) -> BigInt {
+        let limbs = amount / 32;
+        let bits = amount % 32;
+
+        let mut magnitude = vec![0u32; limbs];
+        let mut carry = 0u32;
+        for &limb in &self.magnitude {
+            if bits == 0 {
+                magnitude.push(limb);
+            } else {
+                magnitude.push((limb << bits) | carry);
+                carry = limb >> (32 - bits);
+            }
+        }
+        magnitude.push(carry);
+
+        BigInt::from_parts(self.negative, magnitude)
+    }
+
+    /// Arithmetic shift: negative values round toward negative infinity like `i64 >>`.
+    pub fn shr(&self, amount: usize) -> BigInt {
+        if self.negative {
+            let one = BigInt::from_i64(1);
+            return self.neg().sub(&one).shr(amount).neg().sub(&one);
+        }
+
+        let limbs = amount / 32;
+        let bits = amount % 32;
+        if limbs >= self.magnitude.len() {
+            return BigInt::default();
+        }
+
+        let source = &self.magnitude[limbs..];
+        let magnitude = (0..source.len())
+            .map(|i| {
+                if bits == 0 {
+                    source[i]
+                } else {
+                    let high = source.get(i + 1).map_or(0, |&next| next << (32 - bits));
+                    (source[i] >> bits) | high
+                }
+            })
+            .collect();
+
+        BigInt::from_parts(false, magnitude)
+    }
+
+    fn bitwise(&self, other: &BigInt, op: fn(u32, u32) -> u32) -> BigInt {
+        let length = self.magnitude.len().max(other.magnitude.len()) + 1;
+        let a = self.twos_complement(length);
+        let b = other.twos_complement(length);
+        let result: Vec<u32> = a.iter().zip(b.iter()).map(|(&a, &b)| op(a, b)).collect();
+
+        if result[length - 1] >> 31 == 1 {
+            let inverted: Vec<u32> = result.iter().map(|limb| !limb).collect();
+            BigInt::from_parts(true, add_magnitudes(&inverted, &[1]))
+        } else {
+            BigInt::from_parts(false, result)
+        }
+    }
+
+    fn twos_complement(&self, length: usize) -> Vec<u32> {
+        let mut limbs = self.magnitude.clone();
+        limbs.resize(length, 0);
+
+        if self.negative {
+            let inverted: Vec<u32> = limbs.iter().map(|limb| !limb).collect();
+            limbs = add_magnitudes(&inverted, &[1]);
+            limbs.truncate(length);
+        }
+        limbs
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        const CHUNK: u64 = 1_000_000_000;
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = Vec::new();
+
+        while !magnitude.is_empty() {
+            let mut remainder = 0u64;
+            for limb in magnitude.iter_mut().rev() {
+                let current = (remainder << 32) | *limb as u64;
+                *limb = (current / CHUNK) as u32;
+                remainder = current % CHUNK;
+            }
+            chunks.push(remainder);
+
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (longer, shorter) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(longer.len() + 1);
+    let mut carry = 0u64;
+
+    for (i, &limb) in longer.iter().enumerate() {
+        let total = limb as u64 + shorter.get(i).copied().unwrap_or(0) as u64 + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry != 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+/// Requires `a >= b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, &limb) in a.iter().enumerate() {
+        let mut total = limb as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        borrow = if total < 0 {
+            total += 1 << 32;
+            1
+        } else {
+            0
+        };
+        difference.push(total as u32);
+    }
+    difference
+}
+
+fn div_rem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if compare_magnitudes(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+
+    if let [divisor] = b {
+        let divisor = *divisor as u64;
+        let mut quotient = vec![0u32; a.len()];
+        let mut remainder = 0u64;
+        for i in (0..a.len()).rev() {
+            let current = (remainder << 32) | a[i] as u64;
+            quotient[i] = (current / divisor) as u32;
+            remainder = current % divisor;
+        }
+        return (quotient, vec![remainder as u32]);
+    }
+
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+
+    for i in (0..a.len() * 32).rev() {
+        let mut carry = (a[i / 32] >> (i % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            remainder.push(carry);
+        }
+
+        if compare_magnitudes(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitudes(&remainder, b);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[i / 32] |= 1 << (i % 32);
+        }
+    }
+
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        match text.strip_prefix('-') {
+            Some(digits) => BigInt::parse(digits, 10).unwrap().neg(),
+            None => BigInt::parse(text, 10).unwrap(),
+        }
+    }
+
+    #[test]
+    fn div_rem_truncates_toward_zero() {
+        for (a, b, quotient, remainder) in [(7, 2, 3, 1), (-7, 2, -3, -1), (7, -2, -3, 1), (-7, -2, 3, -1), (1, 7, 0, 1)] {
+            let (q, r) = BigInt::from_i64(a).div_rem(&BigInt::from_i64(b)).unwrap();
+            assert_eq!((q.to_i64(), r.to_i64()), (Some(quotient), Some(remainder)), "{} / {}", a, b);
+            assert_eq!(BigInt::from_i64(a).rem(&BigInt::from_i64(b)).to_i64(), Some(remainder));
+        }
+        assert!(BigInt::from_i64(1).div_rem(&BigInt::default()).is_none());
+    }
+
+    #[test]
+    fn div_rem_multi_limb() {
+        let n = big("-1267650600228229401496703217589");
+        let d = big("1099511627783");
+        let (q, r) = n.div_rem(&d).unwrap();
+        assert_eq!(q.mul(&d).add(&r), n);
+        assert!(r.abs() < d.abs());
+        assert!(r.is_negative());
+
+        assert_eq!(big("1000000000000000000000000000000").div(&big("1000000000000")).to_string(), "1000000000000000000");
+    }
+
+    #[test]
+    fn i64_edges() {
+        let min = BigInt::from_i64(i64::MIN);
+        assert_eq!(min.to_i64(), Some(i64::MIN));
+        assert_eq!(min.neg().to_i64(), None);
+        assert_eq!(min.neg().to_string(), "9223372036854775808");
+        assert_eq!(min.sub(&BigInt::from_i64(1)).to_i64(), None);
+        assert_eq!(BigInt::from_i64(i64::MAX).add(&BigInt::from_i64(1)).to_i64(), None);
+        assert_eq!(BigInt::from_i64(i64::MAX).to_i64(), Some(i64::MAX));
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+        assert_eq!(big("18446744073709551616").to_i64(), None);
+        assert_eq!(BigInt::from_i64(-1).mul(&min).to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn bitwise_matches_i64() {
+        let samples = [0, 1, -1, 3, -6, 255, -256, i64::MAX, i64::MIN, 0x1234_5678_9abc, -0x1234_5678_9abc];
+        for &a in &samples {
+            assert_eq!(BigInt::from_i64(a).bit_not().to_i64(), Some(!a));
+            for &b in &samples {
+                let (x, y) = (BigInt::from_i64(a), BigInt::from_i64(b));
+                assert_eq!(x.bit_and(&y).to_i64(), Some(a & b), "{} & {}", a, b);
+                assert_eq!(x.bit_or(&y).to_i64(), Some(a | b), "{} | {}", a, b);
+                assert_eq!(x.bit_xor(&y).to_i64(), Some(a ^ b), "{} ^ {}", a, b);
+            }
+        }
+        assert_eq!(big("18446744073709551616").bit_not().to_string(), "-18446744073709551617");
+    }
+
+    #[test]
+    fn shifts_on_negatives() {
+        for (value, amount) in [(-1, 1), (-5, 1), (-5, 63), (-6, 2), (5, 1), (i64::MIN, 63), (i64::MIN, 1)] {
+            assert_eq!(BigInt::from_i64(value).shr(amount).to_i64(), Some(value >> amount), "{} >> {}", value, amount);
+        }
+        assert_eq!(big("-18446744073709551616").shr(64).to_i64(), Some(-1));
+        assert_eq!(big("-18446744073709551617").shr(64).to_i64(), Some(-2));
+        assert_eq!(BigInt::from_i64(-1).shr(1000).to_i64(), Some(-1));
+        assert_eq!(BigInt::from_i64(1).shr(1000).to_i64(), Some(0));
+        assert_eq!(BigInt::from_i64(-3).shl(40).to_i64(), Some(-3 << 40));
+        assert_eq!(BigInt::from_i64(-1).shl(64).to_string(), "-18446744073709551616");
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(big("-123456789012345678901234567890").to_string(), "-123456789012345678901234567890");
+        assert_eq!(BigInt::parse("ffffffffffffffffffff", 16).unwrap().to_string(), "1208925819614629174706175");
+        assert_eq!(BigInt::parse("102", 2), None);
+        assert_eq!(big("1000000000").to_string(), "1000000000");
+        assert_eq!(big("-0").to_string(), "0");
+    }
+
+    #[test]
+    fn from_f64_is_exact() {
+        assert_eq!(BigInt::from_f64(1180591620717411303424.0).unwrap().to_string(), "1180591620717411303424");
+        assert_eq!(BigInt::from_f64(-1.0).unwrap().to_i64(), Some(-1));
+        assert_eq!(BigInt::from_f64(-0.0).unwrap().to_i64(), Some(0));
+        assert_eq!(BigInt::from_f64(9007199254740992.0).unwrap().to_i64(), Some(9007199254740992));
+        assert_eq!(BigInt::from_f64(0.5), None);
+        assert_eq!(BigInt::from_f64(f64::NAN), None);
+        assert_eq!(BigInt::from_f64(f64::INFINITY), None);
+    }
+}
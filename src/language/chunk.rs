@@ -22,6 +22,7 @@ impl Chunk {
         self.code.len()
     }
 
+    #[allow(dead_code)]
     pub fn capacity(&self) -> usize {
         self.code.capacity()
     }
@@ -30,6 +31,7 @@ impl Chunk {
         self.lines.len()
     }
 
+    #[allow(dead_code)]
     pub fn line_capacity(&self) -> usize {
         self.lines.capacity()
     }
@@ -46,6 +48,13 @@ impl Chunk {
         });
     }
 
+    pub fn truncate(&mut self, count: usize) {
+        self.code.truncate(count);
+        while self.lines.last().is_some_and(|line| line.offset >= count) {
+            self.lines.pop();
+        }
+    }
+
     pub fn add_constant(&mut self, value: Value) -> usize {
         self.constants.push(value);
         self.constants.len() - 1
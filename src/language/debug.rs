@@ -1,5 +1,7 @@
 use super::opcode::*;
 use super::chunk::*;
+use super::object::*;
+use super::value::*;
 
 pub trait Disassemble {
     fn disassemble(&self, name: &str);
@@ -9,7 +11,7 @@ impl Disassemble for Chunk {
     fn disassemble(&self, name: &str) {
         println!("== {} ==", name);
 
-        let mut offset = 0 as usize;
+        let mut offset: usize = 0;
 
         while offset < self.count() {
             offset = disassemble_instruction(self, offset)
@@ -17,7 +19,7 @@ impl Disassemble for Chunk {
     }
 }
 
-fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
     print!("{:04} ", offset);
 
     let line = chunk.get_line(offset);
@@ -31,16 +33,61 @@ fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
     let instruction = chunk.code[offset];
 
     match OpCode::from(instruction) {
-        OpCode::OpConstant => {
-            let constant = chunk.code[offset + 1];
-            println!("{:?} {:?} '{:.3e}'", OpCode::from(chunk.code[offset]), constant, chunk.constants[constant as usize]);
-            offset + 2
-        },
+        OpCode::OpConstant => { constant_instruction(chunk, offset) },
+        OpCode::OpNull => { simple_instruction(instruction, offset) },
+        OpCode::OpTrue => { simple_instruction(instruction, offset) },
+        OpCode::OpFalse => { simple_instruction(instruction, offset) },
+        OpCode::OpGetLocal => { byte_instruction(chunk, offset) },
+        OpCode::OpSetLocal => { byte_instruction(chunk, offset) },
+        OpCode::OpDefineGlobal => { constant_instruction(chunk, offset) },
+        OpCode::OpGetGlobal => { constant_instruction(chunk, offset) },
+        OpCode::OpSetGlobal => { constant_instruction(chunk, offset) },
+        OpCode::OpGetUpvalue => { byte_instruction(chunk, offset) },
+        OpCode::OpSetUpvalue => { byte_instruction(chunk, offset) },
+        OpCode::OpGetProperty => { constant_instruction(chunk, offset) },
+        OpCode::OpSetProperty => { constant_instruction(chunk, offset) },
+        OpCode::OpGetSuper => { constant_instruction(chunk, offset) },
+        OpCode::OpEqual => { simple_instruction(instruction, offset) },
+        OpCode::OpGreater => { simple_instruction(instruction, offset) },
+        OpCode::OpGreaterEqual => { simple_instruction(instruction, offset) },
+        OpCode::OpLess => { simple_instruction(instruction, offset) },
+        OpCode::OpLessEqual => { simple_instruction(instruction, offset) },
         OpCode::OpAdd => { simple_instruction(instruction, offset) },
         OpCode::OpSubtract => { simple_instruction(instruction, offset) },
         OpCode::OpMultiply => { simple_instruction(instruction, offset) },
         OpCode::OpDivide => { simple_instruction(instruction, offset) },
+        OpCode::OpModulo => { simple_instruction(instruction, offset) },
+        OpCode::OpNot => { simple_instruction(instruction, offset) },
+        OpCode::OpAssertNonNull => { simple_instruction(instruction, offset) },
         OpCode::OpNegate => { simple_instruction(instruction, offset) },
+        OpCode::OpBitAnd => { simple_instruction(instruction, offset) },
+        OpCode::OpBitOr => { simple_instruction(instruction, offset) },
+        OpCode::OpBitXor => { simple_instruction(instruction, offset) },
+        OpCode::OpBitNot => { simple_instruction(instruction, offset) },
+        OpCode::OpShiftLeft => { simple_instruction(instruction, offset) },
+        OpCode::OpShiftRight => { simple_instruction(instruction, offset) },
+        OpCode::OpRange => { simple_instruction(instruction, offset) },
+        OpCode::OpBuildString => { byte_instruction(chunk, offset) },
+        OpCode::OpBuildList => { byte_instruction(chunk, offset) },
+        OpCode::OpBuildMap => { byte_instruction(chunk, offset) },
+        OpCode::OpIndexGet => { simple_instruction(instruction, offset) },
+        OpCode::OpIndexSet => { simple_instruction(instruction, offset) },
+        OpCode::OpPop => { simple_instruction(instruction, offset) },
+        OpCode::OpDup => { simple_instruction(instruction, offset) },
+        OpCode::OpDup2 => { simple_instruction(instruction, offset) },
+        OpCode::OpBury => { byte_instruction(chunk, offset) },
+        OpCode::OpPrint => { simple_instruction(instruction, offset) },
+        OpCode::OpJump => { jump_instruction(chunk, 1, offset) },
+        OpCode::OpJumpIfFalse => { jump_instruction(chunk, 1, offset) },
+        OpCode::OpJumpIfNull => { jump_instruction(chunk, 1, offset) },
+        OpCode::OpLoop => { jump_instruction(chunk, -1, offset) },
+        OpCode::OpForIter => { for_iter_instruction(chunk, offset) },
+        OpCode::OpCall => { byte_instruction(chunk, offset) },
+        OpCode::OpClosure => { closure_instruction(chunk, offset) },
+        OpCode::OpCloseUpvalue => { simple_instruction(instruction, offset) },
+        OpCode::OpClass => { constant_instruction(chunk, offset) },
+        OpCode::OpInherit => { simple_instruction(instruction, offset) },
+        OpCode::OpMethod => { constant_instruction(chunk, offset) },
         OpCode::OpReturn => { simple_instruction(instruction, offset) },
         OpCode::Index(_) => {
             offset + 1
@@ -48,7 +95,56 @@ fn disassemble_instruction(chunk: &Chunk, offset: usize) -> usize {
     }
 }
 
+fn constant_instruction(chunk: &Chunk, offset: usize) -> usize {
+    let constant = chunk.code[offset + 1];
+    println!("{:?} {:?} '{}'", OpCode::from(chunk.code[offset]), constant, chunk.constants[constant as usize]);
+    offset + 2
+}
+
+fn byte_instruction(chunk: &Chunk, offset: usize) -> usize {
+    let slot = chunk.code[offset + 1];
+    println!("{:?} {}", OpCode::from(chunk.code[offset]), slot);
+    offset + 2
+}
+
 fn simple_instruction(instruction: u8, offset: usize) -> usize {
     println!("{:?}", OpCode::from(instruction));
     offset + 1
-}
\ No newline at end of file
+}
+
+fn jump_instruction(chunk: &Chunk, sign: i64, offset: usize) -> usize {
+    let jump = ((chunk.code[offset + 1] as u16) << 8) | chunk.code[offset + 2] as u16;
+    let target = offset as i64 + 3 + sign * jump as i64;
+    println!("{:?} {} -> {}", OpCode::from(chunk.code[offset]), offset, target);
+    offset + 3
+}
+
+fn for_iter_instruction(chunk: &Chunk, offset: usize) -> usize {
+    let slot = chunk.code[offset + 1];
+    let jump = ((chunk.code[offset + 2] as u16) << 8) | chunk.code[offset + 3] as u16;
+    let target = offset + 4 + jump as usize;
+    println!("{:?} {} {} -> {}", OpCode::from(chunk.code[offset]), slot, offset, target);
+    offset + 4
+}
+
+fn closure_instruction(chunk: &Chunk, offset: usize) -> usize {
+    let constant = chunk.code[offset + 1] as usize;
+    println!("{:?} {} '{}'", OpCode::from(chunk.code[offset]), constant, chunk.constants[constant]);
+
+    let upvalue_count = match chunk.constants[constant] {
+        Value::Obj(obj) => match unsafe { &*obj } {
+            Obj::Function(function) => function.upvalue_count,
+            _ => 0,
+        },
+        _ => 0,
+    };
+
+    let mut offset = offset + 2;
+    for _ in 0..upvalue_count {
+        let is_local = chunk.code[offset];
+        let index = chunk.code[offset + 1];
+        println!("{:04}    |                     {} {}", offset, if is_local == 1 { "local" } else { "upvalue" }, index);
+        offset += 2;
+    }
+    offset
+}
@@ -0,0 +1,386 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use super::bigint::*;
+
+/// Extra fractional digits kept when a quotient does not terminate.
+const DIVISION_DIGITS: u32 = 20;
+/// Largest exponent accepted when parsing, so `1e999999999d` cannot exhaust memory.
+const MAX_EXPONENT: i64 = 4096;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Rounding {
+    HalfUp,
+    Floor,
+    Ceiling,
+    Down,
+}
+
+/// Exact base-10 number: `mantissa / 10^scale`. The scale is kept as written, so `1.10d`
+/// prints as `1.10`, while comparisons ignore trailing zeros.
+#[derive(Clone, Debug)]
+pub struct Decimal {
+    mantissa: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn from_bigint(value: BigInt) -> Decimal {
+        Decimal {
+            mantissa: value,
+            scale: 0,
+        }
+    }
+
+    pub fn from_f64(value: f64) -> Option<Decimal> {
+        if value.is_finite() {
+            Decimal::parse(&format!("{:?}", value))
+        } else {
+            None
+        }
+    }
+
+    /// The exact binary value of a float, rather than its shortest decimal spelling.
+    pub fn from_f64_exact(value: f64) -> Option<Decimal> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let (mantissa, shift) = match exponent {
+            0 => (bits & ((1 << 52) - 1), -1074),
+            _ => ((bits & ((1 << 52) - 1)) | (1 << 52), exponent - 1075),
+        };
+        if shift >= 0 {
+            return BigInt::from_f64(value).map(Decimal::from_bigint);
+        }
+
+        // m / 2^k == m * 5^k / 10^k
+        let scale = -shift as u32;
+        let mut mantissa = BigInt::from_i64(mantissa as i64).mul(&power(5, scale));
+        if value < 0.0 {
+            mantissa = mantissa.neg();
+        }
+        Some(Decimal { mantissa, scale }.normalized())
+    }
+
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+
+        let (number, exponent) = match text.find(['e', 'E']) {
+            Some(index) => (&text[..index], text[index + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        if exponent.abs() > MAX_EXPONENT {
+            return None;
+        }
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let mut mantissa = BigInt::parse(&format!("{}{}", whole, fraction), 10)?;
+        if negative {
+            mantissa = mantissa.neg();
+        }
+
+        let scale = fraction.len() as i64 - exponent;
+        if scale < 0 {
+            mantissa = mantissa.mul(&power_of_ten(-scale as u32));
+        }
+
+        Some(Decimal {
+            mantissa,
+            scale: u32::try_from(scale.max(0)).ok()?,
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub fn is_integral(&self) -> bool {
+        self.normalized().scale == 0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn to_bigint(&self) -> BigInt {
+        self.mantissa.div(&power_of_ten(self.scale))
+    }
+
+    pub fn neg(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.neg(),
+            scale: self.scale,
+        }
+    }
+
+    pub fn add(&self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal {
+            mantissa: self.rescaled(scale).add(&other.rescaled(scale)),
+            scale,
+        }
+    }
+
+    pub fn sub(&self, other: &Decimal) -> Decimal {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Decimal) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.mul(&other.mantissa),
+            scale: self.scale + other.scale,
+        }
+    }
+
+    /// Divides exactly when the quotient terminates, otherwise rounds half-up after
+    /// `DIVISION_DIGITS` extra digits. Callers must rule out a zero divisor.
+    pub fn div(&self, other: &Decimal) -> Decimal {
+        let minimum_scale = self.scale.max(other.scale);
+        let scale = minimum_scale + DIVISION_DIGITS;
+
+        let numerator = self.mantissa.mul(&power_of_ten(scale + other.scale - self.scale));
+        let (quotient, remainder) = numerator.div_rem(&other.mantissa).unwrap_or_default();
+        let negative = numerator.is_negative() != other.mantissa.is_negative();
+        Decimal {
+            mantissa: round_quotient(quotient, &remainder, &other.mantissa, Rounding::HalfUp, negative),
+            scale,
+        }
+        .trimmed(minimum_scale)
+    }
+
+    /// Remainder of truncating division, taking the sign of the dividend.
+    pub fn rem(&self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal {
+            mantissa: self.rescaled(scale).rem(&other.rescaled(scale)),
+            scale,
+        }
+    }
+
+    pub fn round(&self, places: u32, rounding: Rounding) -> Decimal {
+        if places >= self.scale {
+            return Decimal {
+                mantissa: self.rescaled(places),
+                scale: places,
+            };
+        }
+
+        let divisor = power_of_ten(self.scale - places);
+        let (quotient, remainder) = self.mantissa.div_rem(&divisor).unwrap_or_default();
+        Decimal {
+            mantissa: round_quotient(quotient, &remainder, &divisor, rounding, self.mantissa.is_negative()),
+            scale: places,
+        }
+    }
+
+    /// Drops trailing fractional zeros, so equal values share one representation.
+    pub fn normalized(&self) -> Decimal {
+        self.clone().trimmed(0)
+    }
+
+    fn trimmed(mut self, minimum_scale: u32) -> Decimal {
+        let ten = BigInt::from_i64(10);
+        while self.scale > minimum_scale {
+            match self.mantissa.div_rem(&ten) {
+                Some((shorter, digit)) if digit.is_zero() => {
+                    self.mantissa = shorter;
+                    self.scale -= 1;
+                }
+                _ => break,
+            }
+        }
+        self
+    }
+
+    fn rescaled(&self, scale: u32) -> BigInt {
+        self.mantissa.mul(&power_of_ten(scale - self.scale))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalized();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.rescaled(scale).cmp(&other.rescaled(scale))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+
+        if self.mantissa.is_negative() {
+            write!(f, "-")?;
+        }
+        if fraction.is_empty() {
+            write!(f, "{}", whole)
+        } else {
+            write!(f, "{}.{}", whole, fraction)
+        }
+    }
+}
+
+fn power_of_ten(exponent: u32) -> BigInt {
+    BigInt::parse(&format!("1{}", "0".repeat(exponent as usize)), 10).unwrap_or_default()
+}
+
+fn power(base: i64, mut exponent: u32) -> BigInt {
+    let mut base = BigInt::from_i64(base);
+    let mut result = BigInt::from_i64(1);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.mul(&base);
+        }
+        base = base.mul(&base);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Applies `rounding` to a truncated quotient given the remainder of the division.
+fn round_quotient(quotient: BigInt, remainder: &BigInt, divisor: &BigInt, rounding: Rounding, negative: bool) -> BigInt {
+    if remainder.is_zero() {
+        return quotient;
+    }
+
+    let away_from_zero = match rounding {
+        Rounding::Down => false,
+        Rounding::Floor => negative,
+        Rounding::Ceiling => !negative,
+        Rounding::HalfUp => remainder.abs().add(&remainder.abs()) >= divisor.abs(),
+    };
+
+    match (away_from_zero, negative) {
+        (false, _) => quotient,
+        (true, false) => quotient.add(&BigInt::from_i64(1)),
+        (true, true) => quotient.sub(&BigInt::from_i64(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    fn hash_of(value: &Decimal) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn division_rounds_half_up() {
+        assert_eq!(dec("1").div(&dec("3")).to_string(), "0.33333333333333333333");
+        assert_eq!(dec("2").div(&dec("3")).to_string(), "0.66666666666666666667");
+        assert_eq!(dec("-2").div(&dec("3")).to_string(), "-0.66666666666666666667");
+        assert_eq!(dec("2").div(&dec("-3")).to_string(), "-0.66666666666666666667");
+        assert_eq!(dec("1.10").div(&dec("2")).to_string(), "0.55");
+        assert_eq!(dec("10").div(&dec("4")).to_string(), "2.5");
+        assert_eq!(dec("1").div(&dec("8")).to_string(), "0.125");
+        assert_eq!(dec("4.00").div(&dec("2")).to_string(), "2.00");
+    }
+
+    #[test]
+    fn remainder_takes_sign_of_dividend() {
+        assert_eq!(dec("7.5").rem(&dec("2")).to_string(), "1.5");
+        assert_eq!(dec("-7.5").rem(&dec("2")).to_string(), "-1.5");
+        assert_eq!(dec("7.5").rem(&dec("-2")).to_string(), "1.5");
+    }
+
+    #[test]
+    fn trailing_zeros_compare_and_hash_equal() {
+        assert_eq!(dec("1.10"), dec("1.1"));
+        assert_eq!(dec("1.000"), Decimal::from_bigint(BigInt::from_i64(1)));
+        assert_eq!(hash_of(&dec("1.10")), hash_of(&dec("1.1")));
+        assert_eq!(hash_of(&dec("100")), hash_of(&dec("1e2")));
+        assert_eq!(dec("1.10").to_string(), "1.10");
+        assert!(dec("1.000").is_integral());
+        assert!(!dec("1.010").is_integral());
+        assert!(dec("-0.5") < dec("-0.25"));
+    }
+
+    #[test]
+    fn rounding_modes_on_negatives() {
+        let cases = [
+            ("-1.25", 1, Rounding::HalfUp, "-1.3"),
+            ("-1.24", 1, Rounding::HalfUp, "-1.2"),
+            ("-1.5", 0, Rounding::HalfUp, "-2"),
+            ("1.005", 2, Rounding::HalfUp, "1.01"),
+            ("-1.21", 1, Rounding::Floor, "-1.3"),
+            ("-1.20", 1, Rounding::Floor, "-1.2"),
+            ("1.29", 1, Rounding::Floor, "1.2"),
+            ("-1.29", 1, Rounding::Ceiling, "-1.2"),
+            ("1.21", 1, Rounding::Ceiling, "1.3"),
+            ("-1.29", 1, Rounding::Down, "-1.2"),
+            ("1.29", 1, Rounding::Down, "1.2"),
+            ("-0.4", 0, Rounding::Floor, "-1"),
+            ("-0.4", 0, Rounding::Ceiling, "0"),
+            ("1.1", 3, Rounding::Down, "1.100"),
+        ];
+        for (value, places, rounding, expected) in cases {
+            assert_eq!(dec(value).round(places, rounding).to_string(), expected, "{} {:?} {}", value, rounding, places);
+        }
+    }
+
+    #[test]
+    fn parse_forms() {
+        assert_eq!(dec("1e3").to_string(), "1000");
+        assert_eq!(dec("1.5e-3").to_string(), "0.0015");
+        assert_eq!(dec("-.5").to_string(), "-0.5");
+        assert_eq!(dec("-0.05").to_string(), "-0.05");
+        assert!(Decimal::parse("abc").is_none());
+        assert!(Decimal::parse("1.2.3").is_none());
+        assert!(Decimal::parse("1e999999").is_none());
+    }
+
+    #[test]
+    fn float_conversions() {
+        assert_eq!(Decimal::from_f64(0.1).unwrap(), dec("0.1"));
+        assert_ne!(Decimal::from_f64_exact(0.1).unwrap(), dec("0.1"));
+        assert!(Decimal::from_f64_exact(0.1).unwrap().to_string().starts_with("0.1000000000000000055511151231257827"));
+        assert_eq!(Decimal::from_f64_exact(-0.5).unwrap(), dec("-0.5"));
+        assert_eq!(Decimal::from_f64_exact(1e20).unwrap(), dec("100000000000000000000"));
+        assert!(Decimal::from_f64_exact(f64::NAN).is_none());
+        assert_eq!(dec("-2.75").to_f64(), -2.75);
+        assert_eq!(dec("-2.75").to_bigint().to_i64(), Some(-2));
+    }
+}
@@ -0,0 +1,118 @@
+use super::bigint::*;
+use super::decimal::*;
+use super::memory::*;
+use super::object::*;
+use super::value::*;
+
+pub fn parse_number(heap: &mut Heap, lexeme: &str) -> Result<Value, String> {
+    let digits: String = lexeme.chars().filter(|&c| c != '_').collect();
+
+    let radix = match digits.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0b" | "0B") => Some(2),
+        Some("0o" | "0O") => Some(8),
+        _ => None,
+    };
+
+    if let Some(radix) = radix {
+        return parse_integer(heap, &digits[2..], radix, lexeme);
+    }
+
+    if let Some(number) = digits.strip_suffix(['d', 'D']) {
+        return match Decimal::parse(number) {
+            Some(value) => Ok(Value::Obj(heap.allocate(Obj::Decimal(value)))),
+            None => Err(format!("Invalid numeric literal '{}'.", lexeme)),
+        };
+    }
+
+    if !digits.contains(['.', 'e', 'E']) {
+        return parse_integer(heap, &digits, 10, lexeme);
+    }
+
+    match digits.parse::<f64>() {
+        Ok(value) if value.is_infinite() => Err(format!("Numeric literal '{}' is too large.", lexeme)),
+        Ok(value) => Ok(Value::Float(value)),
+        Err(_) => Err(format!("Invalid numeric literal '{}'.", lexeme)),
+    }
+}
+
+fn parse_integer(heap: &mut Heap, digits: &str, radix: u32, lexeme: &str) -> Result<Value, String> {
+    match BigInt::parse(digits, radix) {
+        Some(value) => Ok(heap.integer(value)),
+        None => Err(format!("Invalid numeric literal '{}'.", lexeme)),
+    }
+}
+
+pub fn unescape(source: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(source.len());
+    let mut chars = source.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some('"') => result.push('"'),
+            Some('\'') => result.push('\''),
+            Some('\\') => result.push('\\'),
+            Some('$') => result.push('$'),
+            Some('u') => result.push(unicode_escape(&mut chars)?),
+            Some(other) => return Err(format!("Invalid escape sequence '\\{}'.", other)),
+            None => return Err(String::from("Unterminated escape sequence at end of string.")),
+        }
+    }
+
+    Ok(result)
+}
+
+fn unicode_escape(chars: &mut std::str::Chars) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err(String::from("Expect '{' after '\\u' in unicode escape."));
+    }
+
+    let mut digits = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => digits.push(c),
+            None => return Err(format!("Unterminated unicode escape '\\u{{{}'.", digits)),
+        }
+    }
+
+    if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid unicode escape '\\u{{{}}}': expect 1 to 6 hex digits.", digits));
+    }
+
+    u32::from_str_radix(&digits, 16).ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode escape '\\u{{{}}}': not a valid code point.", digits))
+}
+
+pub fn trim_indent(source: &str) -> String {
+    let mut lines: Vec<&str> = source.split('\n').collect();
+
+    if lines.len() > 1 && lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    if lines.len() > 1 && lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    // counted in chars, since indentation may contain multi-byte whitespace
+    let indent = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+
+    lines.iter()
+        .map(|line| if line.trim().is_empty() { String::new() } else { line.chars().skip(indent).collect() })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
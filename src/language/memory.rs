@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use super::bigint::*;
+use super::object::*;
+use super::value::*;
+
+#[derive(Debug, Default)]
+pub struct Heap {
+    objects: Vec<*mut Obj>,
+    strings: HashMap<String, *mut Obj>,
+}
+
+impl Heap {
+    pub fn allocate(&mut self, obj: Obj) -> *mut Obj {
+        let pointer = Box::into_raw(Box::new(obj));
+        self.objects.push(pointer);
+        pointer
+    }
+
+    pub fn integer(&mut self, value: BigInt) -> Value {
+        match value.to_i64() {
+            Some(small) => Value::Int(small),
+            None => Value::Obj(self.allocate(Obj::BigInt(value))),
+        }
+    }
+
+    pub fn copy_string(&mut self, value: &str) -> Value {
+        match self.strings.get(value) {
+            Some(interned) => Value::Obj(*interned),
+            None => self.take_string(String::from(value)),
+        }
+    }
+
+    pub fn take_string(&mut self, value: String) -> Value {
+        if let Some(interned) = self.strings.get(&value) {
+            return Value::Obj(*interned);
+        }
+
+        let pointer = self.allocate(Obj::String(ObjString { value: value.clone() }));
+        self.strings.insert(value, pointer);
+        Value::Obj(pointer)
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        for object in self.objects.drain(..) {
+            unsafe {
+                drop(Box::from_raw(object));
+            }
+        }
+    }
+}
@@ -4,6 +4,12 @@ pub mod debug;
 pub mod line_start;
 pub mod vm;
 pub mod value;
+pub mod bigint;
+pub mod decimal;
+pub mod object;
+pub mod memory;
+pub mod native;
+pub mod literal;
 pub mod scanner;
 pub mod token;
 pub mod parser;
\ No newline at end of file
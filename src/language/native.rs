@@ -0,0 +1,116 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::bigint::*;
+use super::decimal::*;
+use super::memory::*;
+use super::object::*;
+use super::value::*;
+
+const MAX_PLACES: i64 = 1000;
+
+pub fn clock(_heap: &mut Heap, _args: &[Value]) -> Result<Value, String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => Ok(Value::Float(elapsed.as_secs_f64())),
+        Err(_) => Err(String::from("System clock is set before the Unix epoch.")),
+    }
+}
+
+pub fn round(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    rounded(heap, args, Rounding::HalfUp)
+}
+
+pub fn floor(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    rounded(heap, args, Rounding::Floor)
+}
+
+pub fn ceil(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    rounded(heap, args, Rounding::Ceiling)
+}
+
+pub fn trunc(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    rounded(heap, args, Rounding::Down)
+}
+
+pub fn decimal(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    let value = match args[0] {
+        Value::Float(value) => Decimal::from_f64(value),
+        value if value.as_string().is_some() => Decimal::parse(&value.as_string().unwrap().value),
+        value => value.as_decimal(),
+    };
+
+    match value {
+        Some(value) => Ok(Value::Obj(heap.allocate(Obj::Decimal(value)))),
+        None => Err(format!("Cannot convert {} to decimal.", describe(args[0]))),
+    }
+}
+
+pub fn float(_heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    let value = match args[0].as_string() {
+        Some(string) => string.value.trim().parse().ok(),
+        None => args[0].as_float(),
+    };
+
+    value.map(Value::Float).ok_or_else(|| format!("Cannot convert {} to float.", describe(args[0])))
+}
+
+/// Truncates toward zero, matching integer division.
+pub fn int(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    let value = match args[0] {
+        value if value.is_integer() => return Ok(value),
+        Value::Float(value) => BigInt::from_f64(value.trunc()),
+        value if value.is_decimal() => value.as_decimal().map(|value| value.to_bigint()),
+        value => value.as_string().and_then(|string| parse_integer(string.value.trim())),
+    };
+
+    match value {
+        Some(value) => Ok(heap.integer(value)),
+        None => Err(format!("Cannot convert {} to int.", describe(args[0]))),
+    }
+}
+
+pub fn str(heap: &mut Heap, args: &[Value]) -> Result<Value, String> {
+    Ok(heap.take_string(args[0].to_string()))
+}
+
+fn rounded(heap: &mut Heap, args: &[Value], rounding: Rounding) -> Result<Value, String> {
+    let places = match args.get(1) {
+        None => 0,
+        Some(&Value::Int(places)) if (0..=MAX_PLACES).contains(&places) => places as u32,
+        Some(other) => {
+            return Err(format!("Decimal places must be an integer between 0 and {}, got {}.", MAX_PLACES, other));
+        }
+    };
+
+    match args[0] {
+        value if value.is_integer() => Ok(value),
+        // round through the shortest decimal form so round(2.675, 2) gives 2.68 as written
+        Value::Float(value) => match Decimal::from_f64(value) {
+            Some(exact) => Ok(Value::Float(exact.round(places, rounding).to_f64())),
+            None => Ok(Value::Float(value)),
+        },
+        value if value.is_decimal() => {
+            let result = value.as_decimal().unwrap().round(places, rounding);
+            Ok(Value::Obj(heap.allocate(Obj::Decimal(result))))
+        }
+        value => Err(format!("Expected a number but got {}.", describe(value))),
+    }
+}
+
+fn parse_integer(text: &str) -> Option<BigInt> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+
+    let value = BigInt::parse(digits, 10)?;
+    Some(if negative { value.neg() } else { value })
+}
+
+fn describe(value: Value) -> String {
+    match value.as_string() {
+        Some(string) => format!("'{}'", string.value),
+        None => value.to_string(),
+    }
+}
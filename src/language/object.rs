@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::RangeInclusive;
+use super::bigint::*;
+use super::decimal::*;
+use super::chunk::*;
+use super::memory::*;
+use super::value::*;
+
+#[derive(Debug)]
+pub enum Obj {
+    String(ObjString),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    Range(ObjRange),
+    Function(ObjFunction),
+    Native(ObjNative),
+    Closure(ObjClosure),
+    Upvalue(ObjUpvalue),
+    Class(ObjClass),
+    Instance(ObjInstance),
+    BoundMethod(ObjBoundMethod),
+    List(ObjList),
+    Map(ObjMap),
+}
+
+#[derive(Debug)]
+pub struct ObjString {
+    pub value: String,
+}
+
+#[derive(Debug)]
+pub struct ObjRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Debug)]
+pub struct ObjFunction {
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+    pub name: String,
+}
+
+impl ObjFunction {
+    pub fn new(name: String) -> ObjFunction {
+        ObjFunction {
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+            name,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjClosure {
+    pub function: *mut Obj,
+    pub upvalues: Vec<*mut Obj>,
+}
+
+#[derive(Debug)]
+pub struct ObjUpvalue {
+    pub location: *mut Value,
+    pub closed: Value,
+}
+
+#[derive(Debug)]
+pub struct ObjClass {
+    pub name: String,
+    pub methods: HashMap<*mut Obj, Value>,
+}
+
+#[derive(Debug)]
+pub struct ObjInstance {
+    pub class: *mut Obj,
+    pub fields: HashMap<*mut Obj, Value>,
+}
+
+#[derive(Debug)]
+pub struct ObjBoundMethod {
+    pub receiver: Value,
+    pub method: *mut Obj,
+}
+
+#[derive(Debug)]
+pub struct ObjList {
+    pub items: Vec<Value>,
+}
+
+#[derive(Debug, Default)]
+pub struct ObjMap {
+    pub entries: Vec<(Value, Value)>,
+    indices: HashMap<HashKey, usize>,
+}
+
+impl ObjMap {
+    pub fn get(&self, key: HashKey) -> Option<Value> {
+        self.indices.get(&key).map(|&index| self.entries[index].1)
+    }
+
+    pub fn insert(&mut self, key: HashKey, key_value: Value, value: Value) {
+        match self.indices.get(&key) {
+            Some(&index) => self.entries[index].1 = value,
+            None => {
+                self.indices.insert(key, self.entries.len());
+                self.entries.push((key_value, value));
+            }
+        }
+    }
+}
+
+pub type NativeFn = fn(&mut Heap, &[Value]) -> Result<Value, String>;
+
+pub struct ObjNative {
+    pub name: String,
+    pub arity: RangeInclusive<usize>,
+    pub function: NativeFn,
+}
+
+impl Debug for ObjNative {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Display for Obj {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Obj::String(string) => write!(f, "{}", string.value),
+            Obj::BigInt(value) => write!(f, "{}", value),
+            Obj::Decimal(value) => write!(f, "{}", value),
+            Obj::Range(range) => write!(f, "{}..{}", range.start, range.end),
+            Obj::Function(function) => {
+                if function.name.is_empty() {
+                    write!(f, "<script>")
+                } else {
+                    write!(f, "<fn {}>", function.name)
+                }
+            }
+            Obj::Native(native) => write!(f, "<native fn {}>", native.name),
+            Obj::Closure(closure) => unsafe { write!(f, "{}", *closure.function) },
+            Obj::Upvalue(_) => write!(f, "upvalue"),
+            Obj::Class(class) => write!(f, "{}", class.name),
+            Obj::Instance(instance) => unsafe { write!(f, "{} instance", *instance.class) },
+            Obj::BoundMethod(bound) => unsafe { write!(f, "{}", *bound.method) },
+            Obj::List(list) => {
+                write!(f, "[")?;
+                for (i, item) in list.items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Obj::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -1,11 +1,60 @@
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum OpCode {
     OpConstant,
+    OpNull,
+    OpTrue,
+    OpFalse,
+    OpGetLocal,
+    OpSetLocal,
+    OpDefineGlobal,
+    OpGetGlobal,
+    OpSetGlobal,
+    OpGetUpvalue,
+    OpSetUpvalue,
+    OpGetProperty,
+    OpSetProperty,
+    OpGetSuper,
+    OpEqual,
+    OpGreater,
+    OpGreaterEqual,
+    OpLess,
+    OpLessEqual,
     OpAdd,
     OpSubtract,
     OpMultiply,
     OpDivide,
+    OpModulo,
+    OpNot,
+    OpAssertNonNull,
     OpNegate,
+    OpBitAnd,
+    OpBitOr,
+    OpBitXor,
+    OpBitNot,
+    OpShiftLeft,
+    OpShiftRight,
+    OpRange,
+    OpBuildString,
+    OpBuildList,
+    OpBuildMap,
+    OpIndexGet,
+    OpIndexSet,
+    OpPop,
+    OpDup,
+    OpDup2,
+    OpBury,
+    OpPrint,
+    OpJump,
+    OpJumpIfFalse,
+    OpJumpIfNull,
+    OpLoop,
+    OpForIter,
+    OpCall,
+    OpClosure,
+    OpCloseUpvalue,
+    OpClass,
+    OpInherit,
+    OpMethod,
     OpReturn,
     Index(usize),
 }
@@ -14,12 +63,61 @@ impl From<OpCode> for u8 {
     fn from(opcode: OpCode) -> Self {
         (match opcode {
             OpCode::OpConstant => { 1 }
-            OpCode::OpAdd => { 2 }
-            OpCode::OpSubtract => { 3 }
-            OpCode::OpMultiply => { 4 }
-            OpCode::OpDivide => { 5 }
-            OpCode::OpNegate => { 6 }
-            OpCode::OpReturn => { 7 }
+            OpCode::OpNull => { 2 }
+            OpCode::OpTrue => { 3 }
+            OpCode::OpFalse => { 4 }
+            OpCode::OpGetLocal => { 5 }
+            OpCode::OpSetLocal => { 6 }
+            OpCode::OpDefineGlobal => { 7 }
+            OpCode::OpGetGlobal => { 8 }
+            OpCode::OpSetGlobal => { 9 }
+            OpCode::OpGetUpvalue => { 10 }
+            OpCode::OpSetUpvalue => { 11 }
+            OpCode::OpGetProperty => { 12 }
+            OpCode::OpSetProperty => { 13 }
+            OpCode::OpGetSuper => { 14 }
+            OpCode::OpEqual => { 15 }
+            OpCode::OpGreater => { 16 }
+            OpCode::OpGreaterEqual => { 17 }
+            OpCode::OpLess => { 18 }
+            OpCode::OpLessEqual => { 19 }
+            OpCode::OpAdd => { 20 }
+            OpCode::OpSubtract => { 21 }
+            OpCode::OpMultiply => { 22 }
+            OpCode::OpDivide => { 23 }
+            OpCode::OpModulo => { 24 }
+            OpCode::OpNot => { 25 }
+            OpCode::OpAssertNonNull => { 26 }
+            OpCode::OpNegate => { 27 }
+            OpCode::OpBitAnd => { 28 }
+            OpCode::OpBitOr => { 29 }
+            OpCode::OpBitXor => { 30 }
+            OpCode::OpBitNot => { 31 }
+            OpCode::OpShiftLeft => { 32 }
+            OpCode::OpShiftRight => { 33 }
+            OpCode::OpRange => { 34 }
+            OpCode::OpBuildString => { 35 }
+            OpCode::OpBuildList => { 36 }
+            OpCode::OpBuildMap => { 37 }
+            OpCode::OpIndexGet => { 38 }
+            OpCode::OpIndexSet => { 39 }
+            OpCode::OpPop => { 40 }
+            OpCode::OpDup => { 41 }
+            OpCode::OpDup2 => { 42 }
+            OpCode::OpBury => { 43 }
+            OpCode::OpPrint => { 44 }
+            OpCode::OpJump => { 45 }
+            OpCode::OpJumpIfFalse => { 46 }
+            OpCode::OpJumpIfNull => { 47 }
+            OpCode::OpLoop => { 48 }
+            OpCode::OpForIter => { 49 }
+            OpCode::OpCall => { 50 }
+            OpCode::OpClosure => { 51 }
+            OpCode::OpCloseUpvalue => { 52 }
+            OpCode::OpClass => { 53 }
+            OpCode::OpInherit => { 54 }
+            OpCode::OpMethod => { 55 }
+            OpCode::OpReturn => { 56 }
             OpCode::Index(index) => { index }
         }) as u8
     }
@@ -29,12 +127,61 @@ impl From<u8> for OpCode {
     fn from(byte: u8) -> Self {
         match byte {
             1 => OpCode::OpConstant,
-            2 => OpCode::OpAdd,
-            3 => OpCode::OpSubtract,
-            4 => OpCode::OpMultiply,
-            5 => OpCode::OpDivide,
-            6 => OpCode::OpNegate,
-            7 => OpCode::OpReturn,
+            2 => OpCode::OpNull,
+            3 => OpCode::OpTrue,
+            4 => OpCode::OpFalse,
+            5 => OpCode::OpGetLocal,
+            6 => OpCode::OpSetLocal,
+            7 => OpCode::OpDefineGlobal,
+            8 => OpCode::OpGetGlobal,
+            9 => OpCode::OpSetGlobal,
+            10 => OpCode::OpGetUpvalue,
+            11 => OpCode::OpSetUpvalue,
+            12 => OpCode::OpGetProperty,
+            13 => OpCode::OpSetProperty,
+            14 => OpCode::OpGetSuper,
+            15 => OpCode::OpEqual,
+            16 => OpCode::OpGreater,
+            17 => OpCode::OpGreaterEqual,
+            18 => OpCode::OpLess,
+            19 => OpCode::OpLessEqual,
+            20 => OpCode::OpAdd,
+            21 => OpCode::OpSubtract,
+            22 => OpCode::OpMultiply,
+            23 => OpCode::OpDivide,
+            24 => OpCode::OpModulo,
+            25 => OpCode::OpNot,
+            26 => OpCode::OpAssertNonNull,
+            27 => OpCode::OpNegate,
+            28 => OpCode::OpBitAnd,
+            29 => OpCode::OpBitOr,
+            30 => OpCode::OpBitXor,
+            31 => OpCode::OpBitNot,
+            32 => OpCode::OpShiftLeft,
+            33 => OpCode::OpShiftRight,
+            34 => OpCode::OpRange,
+            35 => OpCode::OpBuildString,
+            36 => OpCode::OpBuildList,
+            37 => OpCode::OpBuildMap,
+            38 => OpCode::OpIndexGet,
+            39 => OpCode::OpIndexSet,
+            40 => OpCode::OpPop,
+            41 => OpCode::OpDup,
+            42 => OpCode::OpDup2,
+            43 => OpCode::OpBury,
+            44 => OpCode::OpPrint,
+            45 => OpCode::OpJump,
+            46 => OpCode::OpJumpIfFalse,
+            47 => OpCode::OpJumpIfNull,
+            48 => OpCode::OpLoop,
+            49 => OpCode::OpForIter,
+            50 => OpCode::OpCall,
+            51 => OpCode::OpClosure,
+            52 => OpCode::OpCloseUpvalue,
+            53 => OpCode::OpClass,
+            54 => OpCode::OpInherit,
+            55 => OpCode::OpMethod,
+            56 => OpCode::OpReturn,
             _ => OpCode::Index(byte as usize),
         }
     }
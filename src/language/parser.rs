@@ -1,10 +1,15 @@
+use std::collections::HashSet;
 use std::mem::swap;
-use std::ops::{Deref, DerefMut};
+use std::ops::DerefMut;
 use super::token::*;
 use super::scanner::*;
 use super::chunk::*;
 use super::opcode::*;
 use super::value::*;
+use super::memory::*;
+use super::object::*;
+use super::debug::*;
+use super::literal::*;
 
 #[derive(Debug)]
 pub struct Parser {
@@ -12,6 +17,102 @@ pub struct Parser {
     pub previous: Box<Token>,
     pub had_error: bool,
     pub panic_mode: bool,
+    pub heap: Heap,
+    pub immutable_globals: HashSet<String>,
+    pub echo_expressions: bool,
+    compilers: Vec<Compiler>,
+    classes: Vec<ClassCompiler>,
+    last_target: Option<(usize, Target)>,
+}
+
+#[derive(Debug)]
+struct Local {
+    name: String,
+    depth: i32,
+    mutable: bool,
+    is_captured: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Upvalue {
+    index: usize,
+    is_local: bool,
+}
+
+#[derive(Debug)]
+struct ClassCompiler {
+    has_superclass: bool,
+}
+
+/// Something that can be stored back into. Property and index targets expect their
+/// receiver (and index) to already be on the stack.
+#[derive(Clone, Debug)]
+enum Target {
+    Variable {
+        get_op: OpCode,
+        set_op: OpCode,
+        arg: OpCode,
+        name: String,
+        mutable: bool,
+    },
+    Property(OpCode),
+    Index,
+}
+
+impl Target {
+    fn get_size(&self) -> usize {
+        match self {
+            Target::Variable { .. } | Target::Property(_) => 2,
+            Target::Index => 1,
+        }
+    }
+
+    fn operand_count(&self) -> usize {
+        match self {
+            Target::Variable { .. } => 0,
+            Target::Property(_) => 1,
+            Target::Index => 2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FunctionType {
+    Function,
+    Initializer,
+    Method,
+    Script,
+}
+
+#[derive(Debug)]
+struct Compiler {
+    function: ObjFunction,
+    function_type: FunctionType,
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: i32,
+}
+
+impl Compiler {
+    fn new(function_type: FunctionType, name: String) -> Compiler {
+        let receiver = match function_type {
+            FunctionType::Method | FunctionType::Initializer => String::from("this"),
+            FunctionType::Function | FunctionType::Script => String::new(),
+        };
+
+        Compiler {
+            function: ObjFunction::new(name),
+            function_type,
+            locals: vec![Local {
+                name: receiver,
+                depth: 0,
+                mutable: false,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -22,6 +123,12 @@ enum Precedence {
     And,
     Eq,
     Comp,
+    Elvis,
+    Range,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
     Term,
     Factor,
     Unary,
@@ -38,11 +145,17 @@ impl From<Precedence> for u8 {
             Precedence::And => {3}
             Precedence::Eq => {4}
             Precedence::Comp => {5}
-            Precedence::Term => {6}
-            Precedence::Factor => {7}
-            Precedence::Unary => {8}
-            Precedence::Call => {9}
-            Precedence::Primary => {10}
+            Precedence::Elvis => {6}
+            Precedence::Range => {7}
+            Precedence::BitOr => {8}
+            Precedence::BitXor => {9}
+            Precedence::BitAnd => {10}
+            Precedence::Shift => {11}
+            Precedence::Term => {12}
+            Precedence::Factor => {13}
+            Precedence::Unary => {14}
+            Precedence::Call => {15}
+            Precedence::Primary => {16}
         }
     }
 }
@@ -56,17 +169,23 @@ impl From<u8> for Precedence {
             3 => {Precedence::And}
             4 => {Precedence::Eq}
             5 => {Precedence::Comp}
-            6 => {Precedence::Term}
-            7 => {Precedence::Factor}
-            8 => {Precedence::Unary}
-            9 => {Precedence::Call}
-            10 => {Precedence::Primary}
+            6 => {Precedence::Elvis}
+            7 => {Precedence::Range}
+            8 => {Precedence::BitOr}
+            9 => {Precedence::BitXor}
+            10 => {Precedence::BitAnd}
+            11 => {Precedence::Shift}
+            12 => {Precedence::Term}
+            13 => {Precedence::Factor}
+            14 => {Precedence::Unary}
+            15 => {Precedence::Call}
+            16 => {Precedence::Primary}
             _ => {Precedence::None}
         }
     }
 }
 
-type ParseFn = fn(&mut Parser, &mut Scanner, &mut Chunk) -> ();
+type ParseFn = fn(&mut Parser, &mut Scanner, bool) -> ();
 
 #[derive(Clone, Copy)]
 struct ParseRule {
@@ -76,7 +195,7 @@ struct ParseRule {
 }
 
 impl ParseRule {
-    pub fn new(prefix: Option<ParseFn>, infix: Option<ParseFn>, precedence: Precedence) -> ParseRule {
+    pub const fn new(prefix: Option<ParseFn>, infix: Option<ParseFn>, precedence: Precedence) -> ParseRule {
         ParseRule {
             prefix,
             infix,
@@ -84,7 +203,7 @@ impl ParseRule {
         }
     }
 
-    pub fn empty() -> ParseRule {
+    pub const fn empty() -> ParseRule {
         ParseRule {
             prefix: None,
             infix: None,
@@ -93,63 +212,94 @@ impl ParseRule {
     }
 }
 
-static RULE: [ParseRule; 46] = [
-    ParseRule::new(Some(Parser::grouping), None, Precedence::None),              // (
+static RULE: [ParseRule; 71] = [
+    ParseRule::new(Some(Parser::grouping), Some(Parser::call), Precedence::Call), // (
     ParseRule::empty(),                                                          // )
-    ParseRule::empty(),                                                          // {
+    ParseRule::new(Some(Parser::map), None, Precedence::None),                   // {
     ParseRule::empty(),                                                          // }
-    ParseRule::empty(),                                                          // [
+    ParseRule::new(Some(Parser::list), Some(Parser::subscript), Precedence::Call), // [
     ParseRule::empty(),                                                          // ]
     ParseRule::empty(),                                                          // ,
-    ParseRule::empty(),                                                          // .
+    ParseRule::new(None, Some(Parser::dot), Precedence::Call),                   // .
+    ParseRule::new(None, Some(Parser::binary), Precedence::Range),               // ..
     ParseRule::new(None, Some(Parser::binary), Precedence::Term),                // +
+    ParseRule::new(Some(Parser::pre_increment), Some(Parser::post_increment), Precedence::Call), // ++
+    ParseRule::empty(),                                                          // +=
     ParseRule::new(Some(Parser::unary), Some(Parser::binary), Precedence::Term), // -
+    ParseRule::new(Some(Parser::pre_increment), Some(Parser::post_increment), Precedence::Call), // --
+    ParseRule::empty(),                                                          // -=
     ParseRule::new(None, Some(Parser::binary), Precedence::Factor),              // *
+    ParseRule::empty(),                                                          // *=
     ParseRule::new(None, Some(Parser::binary), Precedence::Factor),              // /
+    ParseRule::empty(),                                                          // /=
+    ParseRule::new(None, Some(Parser::binary), Precedence::Factor),              // %
+    ParseRule::empty(),                                                          // %=
     ParseRule::empty(),                                                          // ;
-    ParseRule::empty(),                                                          // !
-    ParseRule::empty(),                                                          // !=
-    ParseRule::empty(),                                                          // ~
+    ParseRule::empty(),                                                          // :
+    ParseRule::new(None, Some(Parser::safe_call), Precedence::Call),             // ?.
+    ParseRule::new(None, Some(Parser::elvis), Precedence::Elvis),                // ?:
+    ParseRule::new(Some(Parser::unary), None, Precedence::None),                 // !
+    ParseRule::new(Some(Parser::unary), Some(Parser::non_null), Precedence::Call), // !!
+    ParseRule::new(None, Some(Parser::binary), Precedence::Eq),                  // !=
+    ParseRule::new(Some(Parser::unary), None, Precedence::None),                 // ~
     ParseRule::empty(),                                                          // =
-    ParseRule::empty(),                                                          // ==
-    ParseRule::empty(),                                                          // >
-    ParseRule::empty(),                                                          // >=
-    ParseRule::empty(),                                                          // <=
-    ParseRule::empty(),                                                          // <
-    ParseRule::empty(),                                                          // &
-    ParseRule::empty(),                                                          // &&
-    ParseRule::empty(),                                                          // |
-    ParseRule::empty(),                                                          // ||
-    ParseRule::empty(),                                                          // ^
-    ParseRule::empty(),                                                          // identifier
-    ParseRule::empty(),                                                          // string
+    ParseRule::new(None, Some(Parser::binary), Precedence::Eq),                  // ==
+    ParseRule::new(None, Some(Parser::binary), Precedence::Comp),                // >
+    ParseRule::new(None, Some(Parser::binary), Precedence::Comp),                // >=
+    ParseRule::new(None, Some(Parser::binary), Precedence::Comp),                // <=
+    ParseRule::new(None, Some(Parser::binary), Precedence::Comp),                // <
+    ParseRule::new(None, Some(Parser::binary), Precedence::BitAnd),              // &
+    ParseRule::empty(),                                                          // &=
+    ParseRule::new(None, Some(Parser::and), Precedence::And),                    // &&
+    ParseRule::new(None, Some(Parser::binary), Precedence::BitOr),               // |
+    ParseRule::empty(),                                                          // |=
+    ParseRule::new(None, Some(Parser::or), Precedence::Or),                      // ||
+    ParseRule::new(None, Some(Parser::binary), Precedence::BitXor),              // ^
+    ParseRule::empty(),                                                          // ^=
+    ParseRule::new(None, Some(Parser::binary), Precedence::Shift),               // <<
+    ParseRule::empty(),                                                          // <<=
+    ParseRule::new(None, Some(Parser::binary), Precedence::Shift),               // >>
+    ParseRule::empty(),                                                          // >>=
+    ParseRule::new(Some(Parser::variable), None, Precedence::None),              // identifier
+    ParseRule::new(Some(Parser::string), None, Precedence::None),                // string
+    ParseRule::new(Some(Parser::string), None, Precedence::None),                // raw string
+    ParseRule::new(Some(Parser::string), None, Precedence::None),                // multi-line string
+    ParseRule::new(Some(Parser::interpolation), None, Precedence::None),         // interpolation
     ParseRule::new(Some(Parser::number), None, Precedence::None),                // number
-    ParseRule::empty(),                                                          // true
-    ParseRule::empty(),                                                          // false
+    ParseRule::new(Some(Parser::literal), None, Precedence::None),               // true
+    ParseRule::new(Some(Parser::literal), None, Precedence::None),               // false
     ParseRule::empty(),                                                          // if
     ParseRule::empty(),                                                          // else
     ParseRule::empty(),                                                          // return
     ParseRule::empty(),                                                          // while
     ParseRule::empty(),                                                          // for
+    ParseRule::empty(),                                                          // in
     ParseRule::empty(),                                                          // var
     ParseRule::empty(),                                                          // val
-    ParseRule::empty(),                                                          // fun
+    ParseRule::new(Some(Parser::lambda), None, Precedence::None),                // fun
     ParseRule::empty(),                                                          // class
-    ParseRule::empty(),                                                          // null
-    ParseRule::empty(),                                                          // this
-    ParseRule::empty(),                                                          // super
+    ParseRule::new(Some(Parser::literal), None, Precedence::None),               // null
+    ParseRule::new(Some(Parser::this), None, Precedence::None),                  // this
+    ParseRule::new(Some(Parser::super_), None, Precedence::None),                // super
+    ParseRule::empty(),                                                          // print
     ParseRule::empty(),                                                          // error
     ParseRule::empty(),                                                          // eof
 ];
 
 
 impl Parser {
-    pub fn new() -> Parser {
+    pub fn new(heap: Heap, immutable_globals: HashSet<String>) -> Parser {
         Parser {
             current: Box::new(Token::new(TokenType::EOF, String::new(), 0)),
             previous: Box::new(Token::new(TokenType::EOF, String::new(), 0)),
             had_error: false,
             panic_mode: false,
+            heap,
+            immutable_globals,
+            echo_expressions: false,
+            compilers: vec![Compiler::new(FunctionType::Script, String::new())],
+            classes: Vec::new(),
+            last_target: None,
         }
     }
 
@@ -157,7 +307,7 @@ impl Parser {
         swap(self.previous.deref_mut(), self.current.deref_mut());
 
         loop {
-            self.current = Box::new(scanner.scan_token());
+            *self.current = scanner.scan_token();
 
             if self.current.token_type != TokenType::Error {
                 break;
@@ -168,111 +318,1164 @@ impl Parser {
         }
     }
 
-    pub fn expression(&mut self, scanner: &mut Scanner, chunk: &mut Chunk) {
-        self.parse_precedence(Precedence::Assignment);
+    pub fn declaration(&mut self, scanner: &mut Scanner) {
+        if self.match_token(scanner, TokenType::Semicolon) {
+            return;
+        }
+
+        if self.match_token(scanner, TokenType::Class) {
+            self.class_declaration(scanner);
+        } else if self.check(TokenType::Fun) && scanner.next_non_blank() != '(' {
+            self.advance(scanner);
+            self.fun_declaration(scanner);
+        } else if self.match_token(scanner, TokenType::Var) {
+            self.var_declaration(scanner, true);
+        } else if self.match_token(scanner, TokenType::Val) {
+            self.var_declaration(scanner, false);
+        } else {
+            self.statement(scanner);
+        }
+
+        if self.panic_mode {
+            self.synchronize(scanner);
+        }
+    }
+
+    pub fn class_declaration(&mut self, scanner: &mut Scanner) {
+        let global = self.parse_variable(scanner, false, String::from("Expect class name."));
+        let class_name = self.previous.lexme.clone();
+        let name_constant = self.identifier_constant(&class_name);
+
+        self.emit_bytes(OpCode::OpClass, name_constant);
+        self.track_global(class_name.clone(), false);
+        self.define_variable(global);
+
+        self.classes.push(ClassCompiler { has_superclass: false });
+
+        if self.match_token(scanner, TokenType::Colon) {
+            self.consume(scanner, TokenType::Identifier, String::from("Expect superclass name."));
+            let superclass_name = self.previous.lexme.clone();
+            self.variable(scanner, false);
+
+            if superclass_name == class_name {
+                self.error(String::from("A class can't inherit from itself."));
+            }
+
+            self.begin_scope();
+            self.declare_variable(String::from("super"), false);
+            self.define_variable(OpCode::Index(0));
+
+            self.named_variable(scanner, class_name.clone(), false);
+            self.emit_byte(OpCode::OpInherit);
+            self.classes.last_mut().unwrap().has_superclass = true;
+        }
+
+        self.named_variable(scanner, class_name, false);
+        self.skip_newlines(scanner);
+        self.consume(scanner, TokenType::LeftBrace, String::from("Expect '{' before class body."));
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::EOF) {
+            if self.match_token(scanner, TokenType::Semicolon) {
+                continue;
+            }
+            self.method(scanner);
+        }
+        self.consume(scanner, TokenType::RightBrace, String::from("Expect '}' after class body."));
+        self.emit_byte(OpCode::OpPop);
+
+        if self.classes.pop().unwrap().has_superclass {
+            self.end_scope();
+        }
+    }
+
+    fn method(&mut self, scanner: &mut Scanner) {
+        self.consume(scanner, TokenType::Fun, String::from("Expect 'fun' before method."));
+        self.consume(scanner, TokenType::Identifier, String::from("Expect method name."));
+        let name = self.previous.lexme.clone();
+        let constant = self.identifier_constant(&name);
+
+        let function_type = if name == "init" {
+            FunctionType::Initializer
+        } else {
+            FunctionType::Method
+        };
+        self.function(scanner, function_type, name);
+
+        self.emit_bytes(OpCode::OpMethod, constant);
+    }
+
+    pub fn var_declaration(&mut self, scanner: &mut Scanner, mutable: bool) {
+        let global = self.parse_variable(scanner, mutable, String::from("Expect variable name."));
+        let name = self.previous.lexme.clone();
+
+        if self.match_token(scanner, TokenType::EQ) {
+            self.expression(scanner);
+        } else if mutable {
+            self.emit_byte(OpCode::OpNull);
+        } else {
+            self.error(format!("val '{}' must be initialized.", name));
+        }
+        self.consume_end_of_statement(scanner, String::from("Expect newline or ';' after variable declaration."));
+
+        self.track_global(name, mutable);
+        self.define_variable(global);
+    }
+
+    pub fn fun_declaration(&mut self, scanner: &mut Scanner) {
+        let global = self.parse_variable(scanner, false, String::from("Expect function name."));
+        let name = self.previous.lexme.clone();
+
+        self.mark_initialized();
+        self.function(scanner, FunctionType::Function, name.clone());
+
+        self.track_global(name, false);
+        self.define_variable(global);
+    }
+
+    fn function(&mut self, scanner: &mut Scanner, function_type: FunctionType, name: String) {
+        self.compilers.push(Compiler::new(function_type, name));
+        self.begin_scope();
+
+        self.consume(scanner, TokenType::LeftParen, String::from("Expect '(' after function name."));
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.compiler().function.arity += 1;
+                if self.compiler().function.arity > u8::MAX as usize {
+                    self.error_at_current(String::from("Can't have more than 255 parameters."));
+                }
+
+                let constant = self.parse_variable(scanner, false, String::from("Expect parameter name."));
+                self.define_variable(constant);
+
+                if !self.match_token(scanner, TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after parameters."));
+        self.skip_newlines(scanner);
+
+        self.consume(scanner, TokenType::LeftBrace, String::from("Expect '{' before function body."));
+        self.block(scanner);
+
+        let upvalues = self.compiler().upvalues.clone();
+        let function = self.end_compiler();
+        let value = Value::Obj(self.heap.allocate(Obj::Function(function)));
+        let constant = self.make_constant(value);
+        self.emit_bytes(OpCode::OpClosure, constant);
+
+        for upvalue in upvalues {
+            self.emit_bytes(OpCode::Index(upvalue.is_local as usize), OpCode::Index(upvalue.index));
+        }
+    }
+
+    fn track_global(&mut self, name: String, mutable: bool) {
+        if self.compiler().scope_depth > 0 {
+            return;
+        }
+
+        if mutable {
+            self.immutable_globals.remove(&name);
+        } else {
+            self.immutable_globals.insert(name);
+        }
+    }
+
+    pub fn statement(&mut self, scanner: &mut Scanner) {
+        if self.match_token(scanner, TokenType::Print) {
+            self.print_statement(scanner);
+        } else if self.match_token(scanner, TokenType::Return) {
+            self.return_statement(scanner);
+        } else if self.match_token(scanner, TokenType::If) {
+            self.if_statement(scanner);
+        } else if self.match_token(scanner, TokenType::While) {
+            self.while_statement(scanner);
+        } else if self.match_token(scanner, TokenType::For) {
+            self.for_statement(scanner);
+        } else if self.match_token(scanner, TokenType::LeftBrace) {
+            self.begin_scope();
+            self.block(scanner);
+            self.end_scope();
+        } else {
+            self.expression_statement(scanner);
+        }
+    }
+
+    pub fn print_statement(&mut self, scanner: &mut Scanner) {
+        self.expression(scanner);
+        self.consume_end_of_statement(scanner, String::from("Expect newline or ';' after value."));
+        self.emit_byte(OpCode::OpPrint);
+    }
+
+    pub fn return_statement(&mut self, scanner: &mut Scanner) {
+        if self.compiler().function_type == FunctionType::Script {
+            self.error(String::from("Can't return from top-level code."));
+        }
+
+        if self.match_token(scanner, TokenType::Semicolon) || self.check(TokenType::RightBrace) || self.check(TokenType::EOF) {
+            self.emit_return();
+        } else {
+            if self.compiler().function_type == FunctionType::Initializer {
+                self.error(String::from("Can't return a value from an initializer."));
+            }
+
+            self.expression(scanner);
+            self.consume_end_of_statement(scanner, String::from("Expect newline or ';' after return value."));
+            self.emit_byte(OpCode::OpReturn);
+        }
+    }
+
+    pub fn if_statement(&mut self, scanner: &mut Scanner) {
+        self.consume(scanner, TokenType::LeftParen, String::from("Expect '(' after 'if'."));
+        self.expression(scanner);
+        self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after condition."));
+        self.skip_newlines(scanner);
+
+        let then_jump = self.emit_jump(OpCode::OpJumpIfFalse);
+        self.emit_byte(OpCode::OpPop);
+        self.statement(scanner);
+
+        let else_jump = self.emit_jump(OpCode::OpJump);
+
+        self.patch_jump(then_jump);
+        self.emit_byte(OpCode::OpPop);
+
+        if self.match_token(scanner, TokenType::Else) {
+            self.skip_newlines(scanner);
+            self.statement(scanner);
+        }
+        self.patch_jump(else_jump);
+    }
+
+    pub fn while_statement(&mut self, scanner: &mut Scanner) {
+        let loop_start = self.current_chunk().count();
+
+        self.consume(scanner, TokenType::LeftParen, String::from("Expect '(' after 'while'."));
+        self.expression(scanner);
+        self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after condition."));
+        self.skip_newlines(scanner);
+
+        let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse);
+        self.emit_byte(OpCode::OpPop);
+        self.statement(scanner);
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit_byte(OpCode::OpPop);
+    }
+
+    pub fn for_statement(&mut self, scanner: &mut Scanner) {
+        self.begin_scope();
+        self.consume(scanner, TokenType::LeftParen, String::from("Expect '(' after 'for'."));
+
+        if self.check(TokenType::Identifier) && scanner.next_word_is("in") {
+            self.for_in_loop(scanner);
+        } else {
+            self.for_loop(scanner);
+        }
+
+        self.end_scope();
+    }
+
+    fn for_loop(&mut self, scanner: &mut Scanner) {
+        if self.match_token(scanner, TokenType::Semicolon) {
+            // No initializer.
+        } else if self.match_token(scanner, TokenType::Var) {
+            self.var_declaration(scanner, true);
+        } else if self.match_token(scanner, TokenType::Val) {
+            self.var_declaration(scanner, false);
+        } else {
+            self.expression_statement(scanner);
+        }
+
+        let mut loop_start = self.current_chunk().count();
+
+        let mut exit_jump = None;
+        if !self.match_token(scanner, TokenType::Semicolon) {
+            self.expression(scanner);
+            self.consume(scanner, TokenType::Semicolon, String::from("Expect ';' after loop condition."));
+
+            exit_jump = Some(self.emit_jump(OpCode::OpJumpIfFalse));
+            self.emit_byte(OpCode::OpPop);
+        }
+
+        if !self.match_token(scanner, TokenType::RightParen) {
+            let body_jump = self.emit_jump(OpCode::OpJump);
+            let increment_start = self.current_chunk().count();
+
+            self.expression(scanner);
+            self.emit_byte(OpCode::OpPop);
+            self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after for clauses."));
+
+            self.emit_loop(loop_start);
+            loop_start = increment_start;
+            self.patch_jump(body_jump);
+        }
+        self.skip_newlines(scanner);
+
+        self.statement(scanner);
+        self.emit_loop(loop_start);
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+            self.emit_byte(OpCode::OpPop);
+        }
     }
 
-    pub fn grouping(&mut self, scanner: &mut Scanner, chunk: &mut Chunk) {
-        self.expression(scanner, chunk);
+    fn for_in_loop(&mut self, scanner: &mut Scanner) {
+        self.consume(scanner, TokenType::Identifier, String::from("Expect loop variable name."));
+        let name = self.previous.lexme.clone();
+        self.consume(scanner, TokenType::In, String::from("Expect 'in' after loop variable."));
+
+        self.expression(scanner);
+        self.declare_variable(String::from(" iterable"), false);
+        self.mark_initialized();
+        let iterable_slot = self.compiler().locals.len() - 1;
+
+        self.emit_constant(Value::Int(0));
+        self.declare_variable(String::from(" index"), true);
+        self.mark_initialized();
+
+        self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after for clauses."));
+        self.skip_newlines(scanner);
+
+        let loop_start = self.current_chunk().count();
+        let exit_jump = self.emit_for_iter(iterable_slot);
+
+        self.begin_scope();
+        self.declare_variable(name, false);
+        self.mark_initialized();
+        self.statement(scanner);
+        self.end_scope();
+
+        self.emit_loop(loop_start);
+        self.patch_jump(exit_jump);
+    }
+
+    pub fn expression_statement(&mut self, scanner: &mut Scanner) {
+        self.expression(scanner);
+        self.consume_end_of_statement(scanner, String::from("Expect newline or ';' after expression."));
+
+        if self.echo_expressions && self.compilers.len() == 1 && self.compiler().scope_depth == 0 {
+            self.emit_byte(OpCode::OpPrint);
+        } else {
+            self.emit_byte(OpCode::OpPop);
+        }
+    }
+
+    pub fn block(&mut self, scanner: &mut Scanner) {
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::EOF) {
+            self.declaration(scanner);
+        }
+
+        self.consume(scanner, TokenType::RightBrace, String::from("Expect '}' after block."));
+    }
+
+    fn begin_scope(&mut self) {
+        self.compiler().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.compiler().scope_depth -= 1;
+
+        let scope_depth = self.compiler().scope_depth;
+        while let Some(local) = self.compiler().locals.last() {
+            if local.depth <= scope_depth {
+                break;
+            }
+
+            if local.is_captured {
+                self.emit_byte(OpCode::OpCloseUpvalue);
+            } else {
+                self.emit_byte(OpCode::OpPop);
+            }
+            self.compiler().locals.pop();
+        }
+    }
+
+    pub fn expression(&mut self, scanner: &mut Scanner) {
+        self.parse_precedence(scanner, Precedence::Assignment);
+    }
+
+    pub fn grouping(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        self.expression(scanner);
         self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after expression."));
     }
 
-    pub fn unary(&mut self, scanner: &mut Scanner, chunk: &mut Chunk) {
-        let operator_type = &self.previous.token_type;
+    pub fn unary(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let operator_type = self.previous.token_type;
 
-        // self.expression(scanner, chunk);
-        self.parse_precedence(Precedence::Unary);
+        self.parse_precedence(scanner, Precedence::Unary);
 
         match operator_type {
+            TokenType::Bang => {
+                self.emit_byte(OpCode::OpNot);
+            }
+            TokenType::BangBang => {
+                self.emit_bytes(OpCode::OpNot, OpCode::OpNot);
+            }
             TokenType::Minus => {
-                self.emit_byte(chunk, OpCode::OpNegate);
+                self.emit_byte(OpCode::OpNegate);
             }
-            _ => {
-                return;
+            TokenType::BitComplement => {
+                self.emit_byte(OpCode::OpBitNot);
             }
+            _ => {}
         }
     }
 
-    pub fn binary(&mut self, scanner: &mut Scanner, chunk: &mut Chunk) {
-        let operator_type = &self.previous.token_type;
+    pub fn binary(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let operator_type = self.previous.token_type;
         let rule = self.get_rule(operator_type);
 
         let new_precedence = Precedence::from(u8::from(rule.precedence) + 1);
-        self.parse_precedence(new_precedence);
+        self.parse_precedence(scanner, new_precedence);
 
         match operator_type {
             TokenType::Plus => {
-                self.emit_byte(chunk, OpCode::OpAdd);
+                self.emit_byte(OpCode::OpAdd);
             }
             TokenType::Minus => {
-                self.emit_byte(chunk, OpCode::OpSubtract);
+                self.emit_byte(OpCode::OpSubtract);
             }
             TokenType::Star => {
-                self.emit_byte(chunk, OpCode::OpMultiply);
+                self.emit_byte(OpCode::OpMultiply);
             }
             TokenType::Slash => {
-                self.emit_byte(chunk, OpCode::OpDivide);
+                self.emit_byte(OpCode::OpDivide);
+            }
+            TokenType::Percent => {
+                self.emit_byte(OpCode::OpModulo);
+            }
+            TokenType::BangEq => {
+                self.emit_bytes(OpCode::OpEqual, OpCode::OpNot);
+            }
+            TokenType::EqEq => {
+                self.emit_byte(OpCode::OpEqual);
+            }
+            TokenType::Gt => {
+                self.emit_byte(OpCode::OpGreater);
+            }
+            TokenType::Ge => {
+                self.emit_byte(OpCode::OpGreaterEqual);
+            }
+            TokenType::Lt => {
+                self.emit_byte(OpCode::OpLess);
+            }
+            TokenType::Le => {
+                self.emit_byte(OpCode::OpLessEqual);
+            }
+            TokenType::BitAnd => {
+                self.emit_byte(OpCode::OpBitAnd);
+            }
+            TokenType::BitOr => {
+                self.emit_byte(OpCode::OpBitOr);
+            }
+            TokenType::XOR => {
+                self.emit_byte(OpCode::OpBitXor);
+            }
+            TokenType::Shl => {
+                self.emit_byte(OpCode::OpShiftLeft);
+            }
+            TokenType::Shr => {
+                self.emit_byte(OpCode::OpShiftRight);
+            }
+            TokenType::DotDot => {
+                self.emit_byte(OpCode::OpRange);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn dot(&mut self, scanner: &mut Scanner, can_assign: bool) {
+        self.consume(scanner, TokenType::Identifier, String::from("Expect property name after '.'."));
+        let name = self.previous.lexme.clone();
+        let constant = self.identifier_constant(&name);
+
+        if can_assign && self.match_token(scanner, TokenType::EQ) {
+            self.expression(scanner);
+            self.emit_bytes(OpCode::OpSetProperty, constant);
+        } else if let Some(operator) = self.match_compound_assignment(scanner, can_assign) {
+            self.compound_assignment(scanner, Target::Property(constant), operator);
+        } else {
+            self.load_target(Target::Property(constant));
+        }
+    }
+
+    pub fn this(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        if self.classes.is_empty() {
+            self.error(String::from("Can't use 'this' outside of a class."));
+            return;
+        }
+
+        self.named_variable(scanner, String::from("this"), false);
+    }
+
+    pub fn super_(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        match self.classes.last() {
+            None => self.error(String::from("Can't use 'super' outside of a class.")),
+            Some(class) if !class.has_superclass => self.error(String::from("Can't use 'super' in a class with no superclass.")),
+            Some(_) => {}
+        }
+
+        self.consume(scanner, TokenType::Dot, String::from("Expect '.' after 'super'."));
+        self.consume(scanner, TokenType::Identifier, String::from("Expect superclass method name."));
+        let name = self.previous.lexme.clone();
+        let constant = self.identifier_constant(&name);
+
+        self.named_variable(scanner, String::from("this"), false);
+        self.named_variable(scanner, String::from("super"), false);
+        self.emit_bytes(OpCode::OpGetSuper, constant);
+    }
+
+    pub fn lambda(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        self.function(scanner, FunctionType::Function, String::from("anonymous"));
+    }
+
+    pub fn call(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let arg_count = self.argument_list(scanner);
+        self.emit_bytes(OpCode::OpCall, OpCode::Index(arg_count));
+    }
+
+    pub fn list(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let mut item_count = 0;
+
+        while !self.check(TokenType::RightBracket) {
+            self.expression(scanner);
+            if item_count == u8::MAX as usize {
+                self.error(String::from("Can't have more than 255 items in a list literal."));
+            }
+            item_count += 1;
+
+            if !self.match_token(scanner, TokenType::Comma) {
+                break;
+            }
+        }
+
+        self.consume(scanner, TokenType::RightBracket, String::from("Expect ']' after list items."));
+        self.emit_bytes(OpCode::OpBuildList, OpCode::Index(item_count));
+    }
+
+    pub fn map(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let mut entry_count = 0;
+
+        self.skip_newlines(scanner);
+        while !self.check(TokenType::RightBrace) {
+            self.expression(scanner);
+            self.consume(scanner, TokenType::Colon, String::from("Expect ':' after map key."));
+            self.skip_newlines(scanner);
+            self.expression(scanner);
+            if entry_count == u8::MAX as usize {
+                self.error(String::from("Can't have more than 255 entries in a map literal."));
+            }
+            entry_count += 1;
+
+            self.skip_newlines(scanner);
+            if !self.match_token(scanner, TokenType::Comma) {
+                break;
+            }
+            self.skip_newlines(scanner);
+        }
+
+        self.consume(scanner, TokenType::RightBrace, String::from("Expect '}' after map entries."));
+        self.emit_bytes(OpCode::OpBuildMap, OpCode::Index(entry_count));
+    }
+
+    pub fn subscript(&mut self, scanner: &mut Scanner, can_assign: bool) {
+        self.expression(scanner);
+        self.consume(scanner, TokenType::RightBracket, String::from("Expect ']' after index."));
+
+        if can_assign && self.match_token(scanner, TokenType::EQ) {
+            self.expression(scanner);
+            self.emit_byte(OpCode::OpIndexSet);
+        } else if let Some(operator) = self.match_compound_assignment(scanner, can_assign) {
+            self.compound_assignment(scanner, Target::Index, operator);
+        } else {
+            self.load_target(Target::Index);
+        }
+    }
+
+    fn argument_list(&mut self, scanner: &mut Scanner) -> usize {
+        let mut arg_count = 0;
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.expression(scanner);
+                if arg_count == u8::MAX as usize {
+                    self.error(String::from("Can't have more than 255 arguments."));
+                }
+                arg_count += 1;
+
+                if !self.match_token(scanner, TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(scanner, TokenType::RightParen, String::from("Expect ')' after arguments."));
+        arg_count
+    }
+
+    pub fn and(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let end_jump = self.emit_jump(OpCode::OpJumpIfFalse);
+
+        self.emit_byte(OpCode::OpPop);
+        self.parse_precedence(scanner, Precedence::And);
+
+        self.patch_jump(end_jump);
+    }
+
+    pub fn or(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let else_jump = self.emit_jump(OpCode::OpJumpIfFalse);
+        let end_jump = self.emit_jump(OpCode::OpJump);
+
+        self.patch_jump(else_jump);
+        self.emit_byte(OpCode::OpPop);
+
+        self.parse_precedence(scanner, Precedence::Or);
+        self.patch_jump(end_jump);
+    }
+
+    pub fn elvis(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let else_jump = self.emit_jump(OpCode::OpJumpIfNull);
+        let end_jump = self.emit_jump(OpCode::OpJump);
+
+        self.patch_jump(else_jump);
+        self.emit_byte(OpCode::OpPop);
+
+        self.parse_precedence(scanner, Precedence::Elvis);
+        self.patch_jump(end_jump);
+    }
+
+    pub fn safe_call(&mut self, scanner: &mut Scanner, can_assign: bool) {
+        let null_jump = self.emit_jump(OpCode::OpJumpIfNull);
+
+        self.dot(scanner, can_assign);
+        if self.match_token(scanner, TokenType::LeftParen) {
+            self.call(scanner, false);
+        }
+
+        self.patch_jump(null_jump);
+    }
+
+    pub fn non_null(&mut self, _scanner: &mut Scanner, _can_assign: bool) {
+        self.emit_byte(OpCode::OpAssertNonNull);
+    }
+
+    pub fn variable(&mut self, scanner: &mut Scanner, can_assign: bool) {
+        let name = self.previous.lexme.clone();
+        self.named_variable(scanner, name, can_assign);
+    }
+
+    fn named_variable(&mut self, scanner: &mut Scanner, name: String, can_assign: bool) {
+        let current = self.compilers.len() - 1;
+
+        let (get_op, set_op, arg, mutable) = if let Some((slot, mutable)) = self.resolve_local(current, &name) {
+            (OpCode::OpGetLocal, OpCode::OpSetLocal, OpCode::Index(slot), mutable)
+        } else if let Some((index, mutable)) = self.resolve_upvalue(current, &name) {
+            (OpCode::OpGetUpvalue, OpCode::OpSetUpvalue, OpCode::Index(index), mutable)
+        } else {
+            let arg = self.identifier_constant(&name);
+            let mutable = !self.immutable_globals.contains(&name);
+            (OpCode::OpGetGlobal, OpCode::OpSetGlobal, arg, mutable)
+        };
+
+        let target = Target::Variable { get_op, set_op, arg, name, mutable };
+
+        if can_assign && self.match_token(scanner, TokenType::EQ) {
+            self.check_mutable(&target);
+            self.expression(scanner);
+            self.emit_target_set(&target);
+        } else if let Some(operator) = self.match_compound_assignment(scanner, can_assign) {
+            self.compound_assignment(scanner, target, operator);
+        } else {
+            self.load_target(target);
+        }
+    }
+
+    pub fn pre_increment(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let operator = self.increment_operator();
+        self.parse_precedence(scanner, Precedence::Unary);
+
+        if let Some(target) = self.take_target() {
+            self.emit_target_get(&target);
+            self.emit_constant(Value::Int(1));
+            self.emit_byte(operator);
+            self.emit_target_set(&target);
+        }
+    }
+
+    pub fn post_increment(&mut self, _scanner: &mut Scanner, _can_assign: bool) {
+        let operator = self.increment_operator();
+
+        if let Some(target) = self.take_target() {
+            self.emit_target_get(&target);
+            // the old value is the result, so park a copy below the receiver and index
+            self.emit_byte(OpCode::OpDup);
+            if target.operand_count() > 0 {
+                self.emit_bytes(OpCode::OpBury, OpCode::Index(target.operand_count() + 1));
+            }
+            self.emit_constant(Value::Int(1));
+            self.emit_byte(operator);
+            self.emit_target_set(&target);
+            self.emit_byte(OpCode::OpPop);
+        }
+    }
+
+    fn increment_operator(&self) -> OpCode {
+        match self.previous.token_type {
+            TokenType::PlusPlus => OpCode::OpAdd,
+            _ => OpCode::OpSubtract,
+        }
+    }
+
+    fn match_compound_assignment(&mut self, scanner: &mut Scanner, can_assign: bool) -> Option<OpCode> {
+        if !can_assign {
+            return None;
+        }
+
+        let operator = match self.current.token_type {
+            TokenType::PlusEq => OpCode::OpAdd,
+            TokenType::MinusEq => OpCode::OpSubtract,
+            TokenType::StarEq => OpCode::OpMultiply,
+            TokenType::SlashEq => OpCode::OpDivide,
+            TokenType::PercentEq => OpCode::OpModulo,
+            TokenType::BitAndEq => OpCode::OpBitAnd,
+            TokenType::BitOrEq => OpCode::OpBitOr,
+            TokenType::XorEq => OpCode::OpBitXor,
+            TokenType::ShlEq => OpCode::OpShiftLeft,
+            TokenType::ShrEq => OpCode::OpShiftRight,
+            _ => return None,
+        };
+
+        self.advance(scanner);
+        Some(operator)
+    }
+
+    fn compound_assignment(&mut self, scanner: &mut Scanner, target: Target, operator: OpCode) {
+        self.check_mutable(&target);
+        self.emit_target_get(&target);
+        self.expression(scanner);
+        self.emit_byte(operator);
+        self.emit_target_set(&target);
+    }
+
+    /// Emits a plain read and remembers it, so a following `++` or `--` can turn it into a store.
+    fn load_target(&mut self, target: Target) {
+        let start = self.current_chunk().count();
+
+        match &target {
+            Target::Variable { get_op, arg, .. } => self.emit_bytes(*get_op, *arg),
+            Target::Property(constant) => self.emit_bytes(OpCode::OpGetProperty, *constant),
+            Target::Index => self.emit_byte(OpCode::OpIndexGet),
+        }
+
+        self.last_target = Some((start, target));
+    }
+
+    /// Takes back the read emitted by `load_target`, provided nothing has been emitted since.
+    fn take_target(&mut self) -> Option<Target> {
+        match self.last_target.take() {
+            Some((start, target)) if start + target.get_size() == self.current_chunk().count() => {
+                self.current_chunk().truncate(start);
+                self.check_mutable(&target);
+                Some(target)
             }
             _ => {
+                self.error(String::from("Invalid increment or decrement target."));
+                None
+            }
+        }
+    }
+
+    /// Reads the target while leaving its receiver and index on the stack for the store.
+    fn emit_target_get(&mut self, target: &Target) {
+        match target {
+            Target::Variable { get_op, arg, .. } => self.emit_bytes(*get_op, *arg),
+            Target::Property(constant) => {
+                self.emit_byte(OpCode::OpDup);
+                self.emit_bytes(OpCode::OpGetProperty, *constant);
+            }
+            Target::Index => {
+                self.emit_byte(OpCode::OpDup2);
+                self.emit_byte(OpCode::OpIndexGet);
+            }
+        }
+    }
+
+    fn emit_target_set(&mut self, target: &Target) {
+        match target {
+            Target::Variable { set_op, arg, .. } => self.emit_bytes(*set_op, *arg),
+            Target::Property(constant) => self.emit_bytes(OpCode::OpSetProperty, *constant),
+            Target::Index => self.emit_byte(OpCode::OpIndexSet),
+        }
+    }
+
+    fn check_mutable(&mut self, target: &Target) {
+        if let Target::Variable { name, mutable: false, .. } = target {
+            self.error(format!("Cannot reassign val '{}'.", name));
+        }
+    }
+
+    fn resolve_local(&mut self, compiler: usize, name: &str) -> Option<(usize, bool)> {
+        let position = self.compilers[compiler].locals.iter().rposition(|local| local.name == name);
+
+        match position {
+            Some(slot) => {
+                if self.compilers[compiler].locals[slot].depth == -1 {
+                    self.error(format!("Can't read local variable '{}' in its own initializer.", name));
+                }
+                Some((slot, self.compilers[compiler].locals[slot].mutable))
+            }
+            None => None,
+        }
+    }
+
+    fn resolve_upvalue(&mut self, compiler: usize, name: &str) -> Option<(usize, bool)> {
+        if compiler == 0 {
+            return None;
+        }
+
+        if let Some((local, mutable)) = self.resolve_local(compiler - 1, name) {
+            self.compilers[compiler - 1].locals[local].is_captured = true;
+            return Some((self.add_upvalue(compiler, local, true), mutable));
+        }
+
+        if let Some((upvalue, mutable)) = self.resolve_upvalue(compiler - 1, name) {
+            return Some((self.add_upvalue(compiler, upvalue, false), mutable));
+        }
+
+        None
+    }
+
+    fn add_upvalue(&mut self, compiler: usize, index: usize, is_local: bool) -> usize {
+        let upvalues = &self.compilers[compiler].upvalues;
+
+        if let Some(existing) = upvalues.iter().position(|upvalue| upvalue.index == index && upvalue.is_local == is_local) {
+            return existing;
+        }
+
+        if upvalues.len() > u8::MAX as usize {
+            self.error(String::from("Too many closure variables in function."));
+            return 0;
+        }
+
+        self.compilers[compiler].upvalues.push(Upvalue { index, is_local });
+        self.compilers[compiler].upvalues.len() - 1
+    }
+
+    fn parse_precedence(&mut self, scanner: &mut Scanner, precedence: Precedence) {
+        self.advance(scanner);
+
+        let prefix_rule = match self.get_rule(self.previous.token_type).prefix {
+            Some(rule) => rule,
+            None => {
+                self.error(String::from("Expect expression."));
                 return;
             }
+        };
+
+        let can_assign = u8::from(precedence) <= u8::from(Precedence::Assignment);
+        prefix_rule(self, scanner, can_assign);
+
+        while u8::from(precedence) <= u8::from(self.get_rule(self.current.token_type).precedence) {
+            self.advance(scanner);
+            match self.get_rule(self.previous.token_type).infix {
+                Some(infix_rule) => infix_rule(self, scanner, can_assign),
+                None => return,
+            }
         }
+
+        if can_assign && (self.match_token(scanner, TokenType::EQ) || self.match_compound_assignment(scanner, can_assign).is_some()) {
+            self.error(String::from("Invalid assignment target."));
+        }
+    }
+
+    fn parse_variable(&mut self, scanner: &mut Scanner, mutable: bool, message: String) -> OpCode {
+        self.consume(scanner, TokenType::Identifier, message);
+        let name = self.previous.lexme.clone();
+
+        if self.compiler().scope_depth > 0 {
+            self.declare_variable(name, mutable);
+            return OpCode::Index(0);
+        }
+
+        if self.immutable_globals.contains(&name) {
+            self.error(format!("Cannot redeclare val '{}'.", name));
+        }
+
+        self.identifier_constant(&name)
+    }
+
+    fn declare_variable(&mut self, name: String, mutable: bool) {
+        let scope_depth = self.compiler().scope_depth;
+        let redeclared = self.compiler().locals.iter().rev()
+            .take_while(|local| local.depth == -1 || local.depth >= scope_depth)
+            .any(|local| local.name == name);
+
+        if redeclared {
+            self.error(format!("Already a variable named '{}' in this scope.", name));
+        }
+
+        if self.compiler().locals.len() > u8::MAX as usize {
+            self.error(String::from("Too many local variables in function."));
+            return;
+        }
+
+        self.compiler().locals.push(Local {
+            name,
+            depth: -1,
+            mutable,
+            is_captured: false,
+        });
+    }
+
+    fn identifier_constant(&mut self, name: &str) -> OpCode {
+        let value = self.heap.copy_string(name);
+
+        match self.current_chunk().constants.iter().position(|constant| *constant == value) {
+            Some(index) => OpCode::Index(index),
+            None => self.make_constant(value),
+        }
+    }
+
+    fn define_variable(&mut self, global: OpCode) {
+        if self.compiler().scope_depth > 0 {
+            self.mark_initialized();
+            return;
+        }
+
+        self.emit_bytes(OpCode::OpDefineGlobal, global);
     }
 
-    pub fn parse_precedence(&mut self, precedence: Precedence) {
+    fn mark_initialized(&mut self) {
+        let scope_depth = self.compiler().scope_depth;
+        if scope_depth == 0 {
+            return;
+        }
 
+        if let Some(local) = self.compiler().locals.last_mut() {
+            local.depth = scope_depth;
+        }
     }
 
-    pub fn get_rule(&self, token_type: TokenType) -> ParseRule {
+    fn get_rule(&self, token_type: TokenType) -> ParseRule {
         RULE[usize::from(token_type)]
     }
 
-    pub fn number(&mut self, scanner: &mut Scanner, chunk: &mut Chunk) {
-        let value = self.previous.lexme.parse::<Value>().unwrap();
-        self.emit_constant(chunk, value);
+    pub fn number(&mut self, _scanner: &mut Scanner, _can_assign: bool) {
+        match parse_number(&mut self.heap, &self.previous.lexme) {
+            Ok(value) => self.emit_constant(value),
+            Err(message) => self.error(message),
+        }
+    }
+
+    pub fn string(&mut self, _scanner: &mut Scanner, _can_assign: bool) {
+        let contents = match self.previous.token_type {
+            TokenType::RawString => Ok(self.previous.lexme.clone()),
+            TokenType::MultilineString => unescape(&trim_indent(&self.previous.lexme)),
+            _ => unescape(&self.previous.lexme),
+        };
+
+        match contents {
+            Ok(contents) => {
+                let value = self.heap.take_string(contents);
+                self.emit_constant(value);
+            }
+            Err(message) => self.error(message),
+        }
+    }
+
+    pub fn interpolation(&mut self, scanner: &mut Scanner, _can_assign: bool) {
+        let mut part_count = 0;
+
+        loop {
+            if !self.previous.lexme.is_empty() {
+                self.string(scanner, false);
+                part_count += 1;
+            }
+
+            self.expression(scanner);
+            part_count += 1;
+
+            if !self.match_token(scanner, TokenType::Interpolation) {
+                break;
+            }
+        }
+
+        self.consume(scanner, TokenType::String, String::from("Expect end of string interpolation."));
+        if !self.previous.lexme.is_empty() {
+            self.string(scanner, false);
+            part_count += 1;
+        }
+
+        if part_count > u8::MAX as usize {
+            self.error(String::from("Too many parts in string interpolation."));
+        }
+        self.emit_bytes(OpCode::OpBuildString, OpCode::Index(part_count));
+    }
+
+    pub fn literal(&mut self, _scanner: &mut Scanner, _can_assign: bool) {
+        match self.previous.token_type {
+            TokenType::True => self.emit_byte(OpCode::OpTrue),
+            TokenType::False => self.emit_byte(OpCode::OpFalse),
+            TokenType::Null => self.emit_byte(OpCode::OpNull),
+            _ => {}
+        }
+    }
+
+    pub fn emit_byte(&mut self, opcode: OpCode) {
+        let line = self.previous.line;
+        self.current_chunk().write(opcode, line);
+    }
+
+    pub fn emit_bytes(&mut self, opcode1: OpCode, opcode2: OpCode) {
+        self.emit_byte(opcode1);
+        self.emit_byte(opcode2);
+    }
+
+    pub fn emit_loop(&mut self, loop_start: usize) {
+        self.emit_byte(OpCode::OpLoop);
+
+        let offset = self.current_chunk().count() - loop_start + 2;
+        if offset > u16::MAX as usize {
+            self.error(String::from("Loop body too large."));
+        }
+
+        self.emit_bytes(OpCode::Index((offset >> 8) & 0xff), OpCode::Index(offset & 0xff));
+    }
+
+    pub fn emit_for_iter(&mut self, iterable_slot: usize) -> usize {
+        self.emit_bytes(OpCode::OpForIter, OpCode::Index(iterable_slot));
+        self.emit_bytes(OpCode::Index(0xff), OpCode::Index(0xff));
+        self.current_chunk().count() - 2
     }
 
-    pub fn emit_byte(&self, chunk: &mut Chunk, opcode: OpCode) {
-        chunk.write(opcode, self.previous.line);
+    pub fn emit_jump(&mut self, instruction: OpCode) -> usize {
+        self.emit_byte(instruction);
+        self.emit_bytes(OpCode::Index(0xff), OpCode::Index(0xff));
+        self.current_chunk().count() - 2
     }
 
-    pub fn emit_bytes(&self, chunk: &mut Chunk, opcode1: OpCode, opcode2: OpCode) {
-        self.emit_byte(chunk, opcode1);
-        self.emit_byte(chunk, opcode2);
+    pub fn patch_jump(&mut self, offset: usize) {
+        let jump = self.current_chunk().count() - offset - 2;
+
+        if jump > u16::MAX as usize {
+            self.error(String::from("Too much code to jump over."));
+        }
+
+        self.current_chunk().code[offset] = ((jump >> 8) & 0xff) as u8;
+        self.current_chunk().code[offset + 1] = (jump & 0xff) as u8;
+        // the jump now lands after the last read, so it can no longer be rewritten
+        self.last_target = None;
     }
 
-    pub fn emit_return(&self, chunk: &mut Chunk) {
-        self.emit_byte(chunk, OpCode::OpReturn);
+    pub fn emit_return(&mut self) {
+        if self.compiler().function_type == FunctionType::Initializer {
+            self.emit_bytes(OpCode::OpGetLocal, OpCode::Index(0));
+        } else {
+            self.emit_byte(OpCode::OpNull);
+        }
+        self.emit_byte(OpCode::OpReturn);
     }
 
-    pub fn emit_constant(&mut self, chunk: &mut Chunk, value: Value) {
-        let constant = self.make_constant(chunk, value);
-        self.emit_bytes(chunk, OpCode::OpConstant, constant);
+    pub fn emit_constant(&mut self, value: Value) {
+        let constant = self.make_constant(value);
+        self.emit_bytes(OpCode::OpConstant, constant);
     }
 
-    pub fn end_compiler(&self, chunk: &mut Chunk) {
-        self.emit_return(chunk);
+    pub fn end_compiler(&mut self) -> ObjFunction {
+        self.emit_return();
+        self.last_target = None;
+        let mut compiler = self.compilers.pop().unwrap();
+        compiler.function.upvalue_count = compiler.upvalues.len();
+
+        if cfg!(feature = "debug_print_code") && !self.had_error {
+            let name = if compiler.function.name.is_empty() {
+                "<script>"
+            } else {
+                &compiler.function.name
+            };
+            compiler.function.chunk.disassemble(name);
+        }
+
+        compiler.function
     }
 
-    pub fn make_constant(&mut self, chunk: &mut Chunk, value: Value) -> OpCode {
-        let constant = chunk.add_constant(value);
+    fn compiler(&mut self) -> &mut Compiler {
+        self.compilers.last_mut().unwrap()
+    }
+
+    fn current_chunk(&mut self) -> &mut Chunk {
+        &mut self.compiler().function.chunk
+    }
 
-        if constant as u8 > u8::MAX {
+    pub fn make_constant(&mut self, value: Value) -> OpCode {
+        let constant = self.current_chunk().add_constant(value);
+
+        if constant > u8::MAX as usize {
             self.error(String::from("Too many constants in one chunk"));
             return OpCode::Index(0);
         };
 
-        return OpCode::Index(constant);
+        OpCode::Index(constant)
     }
 
     pub fn consume(&mut self, scanner: &mut Scanner, token_type: TokenType, message: String) {
         if self.current.token_type == token_type {
             self.advance(scanner);
+            return;
+        }
+
+        self.error_at_current(message);
+    }
+
+    pub fn consume_end_of_statement(&mut self, scanner: &mut Scanner, message: String) {
+        if self.match_token(scanner, TokenType::Semicolon) {
+            return;
+        }
+
+        if self.check(TokenType::RightBrace) || self.check(TokenType::Else) || self.check(TokenType::EOF) {
+            return;
         }
 
         self.error_at_current(message);
     }
 
+    pub fn skip_newlines(&mut self, scanner: &mut Scanner) {
+        while self.match_token(scanner, TokenType::Semicolon) {}
+    }
+
+    pub fn check(&self, token_type: TokenType) -> bool {
+        self.current.token_type == token_type
+    }
+
+    pub fn match_token(&mut self, scanner: &mut Scanner, token_type: TokenType) -> bool {
+        if self.current.token_type != token_type {
+            return false;
+        }
+        self.advance(scanner);
+        true
+    }
+
+    pub fn synchronize(&mut self, scanner: &mut Scanner) {
+        self.panic_mode = false;
+
+        while self.current.token_type != TokenType::EOF {
+            if self.previous.token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.current.token_type {
+                TokenType::Class | TokenType::Fun | TokenType::Var | TokenType::Val | TokenType::For |
+                TokenType::If | TokenType::While | TokenType::Print | TokenType::Return |
+                TokenType::RightBrace => return,
+                _ => {}
+            }
+
+            self.advance(scanner);
+        }
+    }
+
     pub fn error_at_current(&mut self, message: String) {
         if self.panic_mode {
             return;
@@ -291,15 +1494,15 @@ impl Parser {
         Parser::error_at(&self.previous, message);
     }
 
-    fn error_at(token: *const Box<Token>, message: String) {
-        unsafe {
-            eprint!("[line {}] Error", (**token).line);
+    fn error_at(token: &Token, message: String) {
+        eprint!("[line {}] Error", token.line);
 
-            if (**token).token_type == TokenType::EOF {
-                eprint!(" at end");
-            } else if (**token).token_type == TokenType::Error {} else {
-                eprint!(" at '{}'", (**token).lexme);
-            }
+        if token.token_type == TokenType::EOF {
+            eprint!(" at end");
+        } else if token.token_type == TokenType::Error {} else if token.lexme == "\n" {
+            eprint!(" at end of line");
+        } else {
+            eprint!(" at '{}'", token.lexme);
         }
 
         eprintln!(": {}", message);
@@ -1,23 +1,45 @@
 use super::token::*;
 
 pub struct Scanner {
-    source: String,
+    source: Vec<char>,
     start_index: usize,
     current_index: usize,
     line: i32,
+    /// Open `(`/`[` count per brace level; each `{` starts a level where newlines count again.
+    grouping_depths: Vec<usize>,
+    interpolations: Vec<usize>,
+    shorthand_interpolation: bool,
+    resume_string: bool,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Scanner {
         Scanner {
-            source,
+            source: source.chars().collect(),
             start_index: 0,
             current_index: 0,
-            line: 1
+            line: 1,
+            grouping_depths: vec![0],
+            interpolations: Vec::new(),
+            shorthand_interpolation: false,
+            resume_string: false,
         }
     }
 
     pub fn scan_token(&mut self) -> Token {
+        if self.shorthand_interpolation {
+            self.shorthand_interpolation = false;
+            self.resume_string = true;
+            self.start_index = self.current_index;
+            self.advance();
+            return self.make_identifier();
+        }
+
+        if self.resume_string {
+            self.resume_string = false;
+            return self.make_string();
+        }
+
         self.skip_whitespace();
         self.start_index = self.current_index;
         if self.is_at_end() {
@@ -28,27 +50,131 @@ impl Scanner {
 
         match char {
             '0'..='9' => self.make_number(),
-            'a'..='z' | 'A'..='Z' => self.make_identifier(),
-            '(' => self.make_token(TokenType::LeftParen),
-            ')' => self.make_token(TokenType::RightParen),
-            '{' => self.make_token(TokenType::LeftBrace),
-            '}' => self.make_token(TokenType::RightBrace),
-            '[' => self.make_token(TokenType::LeftBracket),
-            ']' => self.make_token(TokenType::RightBracket),
+            'r' if self.peek() == '"' => self.make_raw_string(),
+            'a'..='z' | 'A'..='Z' | '_' => self.make_identifier(),
+            '(' => {
+                self.open_grouping();
+                self.make_token(TokenType::LeftParen)
+            }
+            ')' => {
+                self.close_grouping();
+                self.make_token(TokenType::RightParen)
+            }
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.grouping_depths.push(0);
+                self.make_token(TokenType::LeftBrace)
+            }
+            '}' => {
+                match self.interpolations.last_mut() {
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.close_grouping();
+
+                        let before = self.source[..self.start_index].iter().rev().skip_while(|c| c.is_whitespace());
+                        if before.take(2).eq(['{', '$'].iter()) {
+                            self.resume_string = true;
+                            return self.error_token(String::from("Expect expression in string interpolation."));
+                        }
+                        return self.make_string();
+                    }
+                    Some(depth) => *depth -= 1,
+                    None => {}
+                }
+                if self.grouping_depths.len() > 1 {
+                    self.grouping_depths.pop();
+                }
+                self.make_token(TokenType::RightBrace)
+            }
+            '[' => {
+                self.open_grouping();
+                self.make_token(TokenType::LeftBracket)
+            }
+            ']' => {
+                self.close_grouping();
+                self.make_token(TokenType::RightBracket)
+            }
             ';' => self.make_token(TokenType::Semicolon),
+            ':' => self.make_token(TokenType::Colon),
+            '?' => {
+                if self.match_char('.') {
+                    self.make_token(TokenType::QuestionDot)
+                } else if self.match_char(':') {
+                    self.make_token(TokenType::Elvis)
+                } else {
+                    self.error_token(String::from("Unexpected character: ?"))
+                }
+            }
             '\n' => {
+                let token = self.make_token(TokenType::Semicolon);
                 self.line += 1;
-                self.make_token(TokenType::Semicolon)
+
+                if self.next_word_is("else") {
+                    return self.scan_token();
+                }
+
+                token
+            }
+            '.' => {
+                let token = if self.match_char('.') {
+                    TokenType::DotDot
+                } else {
+                    TokenType::Dot
+                };
+                self.make_token(token)
             }
-            '.' => self.make_token(TokenType::Dot),
             ',' => self.make_token(TokenType::Comma),
-            '+' => self.make_token(TokenType::Plus),
-            '-' => self.make_token(TokenType::Minus),
-            '*' => self.make_token(TokenType::Star),
-            '/' => self.make_token(TokenType::Slash),
+            '+' => {
+                let token = if self.match_char('+') {
+                    TokenType::PlusPlus
+                } else if self.match_char('=') {
+                    TokenType::PlusEq
+                } else {
+                    TokenType::Plus
+                };
+                self.make_token(token)
+            },
+            '-' => {
+                let token = if self.match_char('-') {
+                    TokenType::MinusMinus
+                } else if self.match_char('=') {
+                    TokenType::MinusEq
+                } else {
+                    TokenType::Minus
+                };
+                self.make_token(token)
+            },
+            '*' => {
+                let token = if self.match_char('=') {
+                    TokenType::StarEq
+                } else {
+                    TokenType::Star
+                };
+                self.make_token(token)
+            },
+            '/' => {
+                let token = if self.match_char('=') {
+                    TokenType::SlashEq
+                } else {
+                    TokenType::Slash
+                };
+                self.make_token(token)
+            },
+            '%' => {
+                let token = if self.match_char('=') {
+                    TokenType::PercentEq
+                } else {
+                    TokenType::Percent
+                };
+                self.make_token(token)
+            },
             '!' => {
                 let token = if self.match_char('=') {
                     TokenType::BangEq
+                } else if self.match_char('!') {
+                    TokenType::BangBang
                 } else {
                     TokenType::Bang
                 };
@@ -65,6 +191,12 @@ impl Scanner {
             '<' => {
                 let token= if self.match_char('=') {
                     TokenType::Le
+                } else if self.match_char('<') {
+                    if self.match_char('=') {
+                        TokenType::ShlEq
+                    } else {
+                        TokenType::Shl
+                    }
                 } else {
                     TokenType::Lt
                 };
@@ -73,11 +205,47 @@ impl Scanner {
             '>' => {
                 let token = if self.match_char('=') {
                     TokenType::Ge
+                } else if self.match_char('>') {
+                    if self.match_char('=') {
+                        TokenType::ShrEq
+                    } else {
+                        TokenType::Shr
+                    }
                 } else {
                     TokenType::Gt
                 };
                 self.make_token(token)
             },
+            '&' => {
+                let token = if self.match_char('&') {
+                    TokenType::And
+                } else if self.match_char('=') {
+                    TokenType::BitAndEq
+                } else {
+                    TokenType::BitAnd
+                };
+                self.make_token(token)
+            },
+            '|' => {
+                let token = if self.match_char('|') {
+                    TokenType::Or
+                } else if self.match_char('=') {
+                    TokenType::BitOrEq
+                } else {
+                    TokenType::BitOr
+                };
+                self.make_token(token)
+            },
+            '^' => {
+                let token = if self.match_char('=') {
+                    TokenType::XorEq
+                } else {
+                    TokenType::XOR
+                };
+                self.make_token(token)
+            },
+            '~' => self.make_token(TokenType::BitComplement),
+            '"' if self.peek() == '"' && self.peek_next() == '"' => self.make_multiline_string(),
             '"' => self.make_string(),
             err_char => {
                 let mut msg = String::from("Unexpected character: ");
@@ -87,18 +255,30 @@ impl Scanner {
         }
     }
 
+    fn open_grouping(&mut self) {
+        if let Some(depth) = self.grouping_depths.last_mut() {
+            *depth += 1;
+        }
+    }
+
+    fn close_grouping(&mut self) {
+        if let Some(depth) = self.grouping_depths.last_mut() {
+            *depth = depth.saturating_sub(1);
+        }
+    }
+
     pub fn skip_whitespace(&mut self) {
         loop {
             let c = self.peek();
             match c {
                 ' ' | '\r' | '\t' => { self.advance(); }
-                '/' => {
-                    if self.peek() == '/' {
-                        while self.peek() != '\n' && !self.is_at_end() {
-                            self.advance();
-                        }
-                    } else {
-                        return;
+                '\n' if self.grouping_depths.last().is_some_and(|&depth| depth > 0) => {
+                    self.line += 1;
+                    self.advance();
+                }
+                '/' if self.peek_next() == '/' => {
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
                     }
                 }
                 _ => return,
@@ -106,6 +286,30 @@ impl Scanner {
         }
     }
 
+    fn next_non_blank_index(&self) -> usize {
+        let mut index = self.current_index;
+
+        while let Some(c) = self.source.get(index) {
+            match c {
+                ' ' | '\r' | '\t' | '\n' => index += 1,
+                _ => break,
+            }
+        }
+
+        index
+    }
+
+    pub fn next_non_blank(&self) -> char {
+        self.source.get(self.next_non_blank_index()).copied().unwrap_or_default()
+    }
+
+    pub fn next_word_is(&self, word: &str) -> bool {
+        let rest = &self.source[self.next_non_blank_index()..];
+        let length = word.chars().count();
+        rest.len() >= length && rest[..length].iter().copied().eq(word.chars()) &&
+            !rest.get(length).is_some_and(|c| c.is_alphanumeric() || *c == '_')
+    }
+
     pub fn error_token(&self, message: String) -> Token {
         Token::new(
             TokenType::Error,
@@ -117,7 +321,7 @@ impl Scanner {
     pub fn make_token(&self, token: TokenType) -> Token {
         Token::new(
             token,
-            self.source[self.start_index..self.current_index].parse().unwrap(),
+            self.source[self.start_index..self.current_index].iter().collect(),
             self.line,
         )
     }
@@ -128,7 +332,7 @@ impl Scanner {
 
     pub fn advance(&mut self) -> char {
         self.current_index += 1;
-        self.source.chars().nth(self.current_index - 1).unwrap()
+        self.source[self.current_index - 1]
     }
 
     pub fn match_char(&mut self, expected: char) -> bool {
@@ -136,13 +340,13 @@ impl Scanner {
             return false;
         }
 
-        if self.source.chars().nth(self.current_index).unwrap() != expected {
+        if self.source[self.current_index] != expected {
             return false;
         }
 
         self.current_index += 1;
 
-        return true;
+        true
     }
 
     pub fn peek(&self) -> char {
@@ -154,11 +358,60 @@ impl Scanner {
     }
 
     pub fn peek_n(&self, n: usize) -> char {
-        self.source.chars().nth(self.current_index + n).unwrap_or_default()
+        self.source.get(self.current_index + n).copied().unwrap_or_default()
     }
 
     pub fn make_string(&mut self) -> Token {
-        while self.peek() != '"' && self.is_at_end() {
+        let content_start = self.current_index;
+
+        loop {
+            if self.is_at_end() {
+                return self.error_token(String::from("Unterminated String"));
+            }
+
+            match self.peek() {
+                '"' => {
+                    let token = self.make_segment(TokenType::String, content_start);
+                    self.advance();
+                    return token;
+                }
+                '$' if self.peek_next() == '{' => {
+                    let token = self.make_segment(TokenType::Interpolation, content_start);
+                    self.current_index += 2;
+                    self.interpolations.push(0);
+                    self.open_grouping();
+                    return token;
+                }
+                '$' if self.peek_next().is_alphabetic() || self.peek_next() == '_' => {
+                    let token = self.make_segment(TokenType::Interpolation, content_start);
+                    self.advance();
+                    self.shorthand_interpolation = true;
+                    return token;
+                }
+                '\\' => {
+                    self.advance();
+                    if self.match_char('\n') {
+                        self.line += 1;
+                    } else if !self.is_at_end() {
+                        self.advance();
+                    }
+                }
+                '\n' => {
+                    self.line += 1;
+                    self.advance();
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    pub fn make_raw_string(&mut self) -> Token {
+        self.advance();
+        let content_start = self.current_index;
+
+        while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1;
             }
@@ -166,32 +419,135 @@ impl Scanner {
         }
 
         if self.is_at_end() {
-            return self.error_token(String::from("Unterminated String"))
+            return self.error_token(String::from("Unterminated raw string"));
         }
 
+        let token = self.make_segment(TokenType::RawString, content_start);
         self.advance();
+        token
+    }
+
+    pub fn make_multiline_string(&mut self) -> Token {
+        self.current_index += 2;
+        let content_start = self.current_index;
+
+        while !(self.peek() == '"' && self.peek_next() == '"' && self.peek_n(2) == '"') {
+            if self.is_at_end() {
+                return self.error_token(String::from("Unterminated multi-line string"));
+            }
 
-        self.make_token(TokenType::String)
+            let mut c = self.advance();
+            if c == '\\' && !self.is_at_end() {
+                c = self.advance();
+            }
+
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+
+        let token = self.make_segment(TokenType::MultilineString, content_start);
+        self.current_index += 3;
+        token
+    }
+
+    fn make_segment(&self, token: TokenType, content_start: usize) -> Token {
+        Token::new(
+            token,
+            self.source[content_start..self.current_index].iter().collect(),
+            self.line,
+        )
     }
 
     pub fn make_number(&mut self) -> Token {
-        while self.peek().is_numeric() {
+        if self.source[self.start_index] == '0' {
+            let radix = match self.peek() {
+                'x' | 'X' => Some((16, "hexadecimal literal")),
+                'b' | 'B' => Some((2, "binary literal")),
+                'o' | 'O' => Some((8, "octal literal")),
+                _ => None,
+            };
+
+            if let Some((radix, kind)) = radix {
+                self.advance();
+                if let Err(message) = self.scan_digits(radix, kind) {
+                    return self.number_error(message);
+                }
+                return self.finish_number();
+            }
+        }
+
+        self.current_index = self.start_index;
+        if let Err(message) = self.scan_digits(10, "numeric literal") {
+            return self.number_error(message);
+        }
+
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
+            if let Err(message) = self.scan_digits(10, "fraction") {
+                return self.number_error(message);
+            }
         }
 
-        if self.peek() == '.' && self.peek_next().is_numeric() {
+        if self.peek() == 'e' || self.peek() == 'E' {
             self.advance();
+            if !self.match_char('+') {
+                self.match_char('-');
+            }
+            if let Err(message) = self.scan_digits(10, "exponent") {
+                return self.number_error(message);
+            }
+        }
 
-            while self.peek().is_numeric() {
-                self.advance();
+        if self.peek() == 'd' || self.peek() == 'D' {
+            self.advance();
+        }
+
+        self.finish_number()
+    }
+
+    fn scan_digits(&mut self, radix: u32, kind: &str) -> Result<(), String> {
+        let start = self.current_index;
+
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            let c = self.peek();
+            if c != '_' && !c.is_digit(radix) {
+                if radix == 10 {
+                    break;
+                }
+                return Err(format!("Invalid digit '{}' in {}.", c, kind));
             }
+            self.advance();
+        }
+
+        let digits = &self.source[start..self.current_index];
+        match (digits.first(), digits.last()) {
+            (None, _) => Err(format!("Expect digits in {}.", kind)),
+            (Some('_'), _) => Err(format!("'_' must be between digits in {}.", kind)),
+            (_, Some('_')) => Err(format!("{} cannot end with '_'.", capitalize(kind))),
+            _ => Ok(()),
+        }
+    }
+
+    fn finish_number(&mut self) -> Token {
+        let c = self.peek();
+        if c.is_alphanumeric() || c == '_' {
+            return self.number_error(format!("Invalid character '{}' in numeric literal.", c));
         }
 
         self.make_token(TokenType::Number)
     }
 
+    fn number_error(&mut self, message: String) -> Token {
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+
+        self.error_token(message)
+    }
+
     pub fn make_identifier(&mut self) -> Token {
-        while self.peek().is_alphabetic() || self.peek().is_numeric() {
+        while self.peek().is_alphabetic() || self.peek().is_numeric() || self.peek() == '_' {
             self.advance();
         }
 
@@ -200,54 +556,82 @@ impl Scanner {
     }
 
     pub fn identifier_type(&mut self) -> TokenType {
-        unsafe {
-            match self.source.chars().nth(self.start_index).unwrap() {
-                'c' => self.match_keyword(1, String::from("lass"), TokenType::Class),
-                'e' => self.match_keyword(1, String::from("lse"), TokenType::Else),
-                'i' => self.match_keyword(1, String::from("f"), TokenType::If),
-                'n' => self.match_keyword(1, String::from("ull"), TokenType::Null),
-                'r' => self.match_keyword(1, String::from("eturn"), TokenType::Return),
-                's' => self.match_keyword(1, String::from("uper"), TokenType::Super),
-                'w' => self.match_keyword(1, String::from("hile"), TokenType::While),
-                'f' => {
-                    if self.current_index - self.start_index > 1 {
-                        match self.source.chars().nth(self.start_index + 1).unwrap() {
-                            'a' => self.match_keyword(2, String::from("lse"), TokenType::False),
-                            'u' => self.match_keyword(2, String::from("n"), TokenType::Fun),
-                            'o' => self.match_keyword(2, String::from("r"), TokenType::For),
-                            _ => TokenType::Identifier
-                        }
-                    } else {
-                        TokenType::Identifier
+        match self.source[self.start_index] {
+            'c' => self.match_keyword(1, String::from("lass"), TokenType::Class),
+            'e' => self.match_keyword(1, String::from("lse"), TokenType::Else),
+            'i' => {
+                if self.current_index - self.start_index > 1 {
+                    match self.source[self.start_index + 1] {
+                        'f' => self.match_keyword(2, String::from(""), TokenType::If),
+                        'n' => self.match_keyword(2, String::from(""), TokenType::In),
+                        _ => TokenType::Identifier
                     }
+                } else {
+                    TokenType::Identifier
                 }
-                'v' => {
-                    if self.current_index - self.start_index > 2 {
-                        match self.source.chars().nth(self.start_index + 1).unwrap() {
-                            'a' => {
-                                match self.source.chars().nth(self.start_index + 2).unwrap() {
-                                    'r' => TokenType::Var,
-                                    'l' => TokenType::Val,
-                                    _ => TokenType::Identifier
-                                }
+            }
+            'n' => self.match_keyword(1, String::from("ull"), TokenType::Null),
+            'p' => self.match_keyword(1, String::from("rint"), TokenType::Print),
+            'r' => self.match_keyword(1, String::from("eturn"), TokenType::Return),
+            's' => self.match_keyword(1, String::from("uper"), TokenType::Super),
+            'w' => self.match_keyword(1, String::from("hile"), TokenType::While),
+            'f' => {
+                if self.current_index - self.start_index > 1 {
+                    match self.source[self.start_index + 1] {
+                        'a' => self.match_keyword(2, String::from("lse"), TokenType::False),
+                        'u' => self.match_keyword(2, String::from("n"), TokenType::Fun),
+                        'o' => self.match_keyword(2, String::from("r"), TokenType::For),
+                        _ => TokenType::Identifier
+                    }
+                } else {
+                    TokenType::Identifier
+                }
+            }
+            't' => {
+                if self.current_index - self.start_index > 1 {
+                    match self.source[self.start_index + 1] {
+                        'r' => self.match_keyword(2, String::from("ue"), TokenType::True),
+                        'h' => self.match_keyword(2, String::from("is"), TokenType::This),
+                        _ => TokenType::Identifier
+                    }
+                } else {
+                    TokenType::Identifier
+                }
+            }
+            'v' => {
+                if self.current_index - self.start_index > 2 {
+                    match self.source[self.start_index + 1] {
+                        'a' => {
+                            match self.source[self.start_index + 2] {
+                                'r' => self.match_keyword(2, String::from("r"), TokenType::Var),
+                                'l' => self.match_keyword(2, String::from("l"), TokenType::Val),
+                                _ => TokenType::Identifier
                             }
-                            _ => TokenType::Identifier
                         }
-                    } else {
-                        TokenType::Identifier
+                        _ => TokenType::Identifier
                     }
+                } else {
+                    TokenType::Identifier
                 }
-                _ => TokenType::Identifier
             }
+            _ => TokenType::Identifier
         }
     }
 
     pub fn match_keyword(&self, start_index: usize, rest: String, return_type: TokenType) -> TokenType {
         if self.current_index - self.start_index == start_index + rest.len() &&
-            self.source[(self.start_index + start_index)..(self.start_index + start_index + rest.len())] == rest {
+            self.source[(self.start_index + start_index)..(self.start_index + start_index + rest.len())].iter().copied().eq(rest.chars()) {
             return_type
         } else {
             TokenType::Identifier
         }
     }
-}
\ No newline at end of file
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -5,31 +5,37 @@ pub struct Token {
     pub line: i32
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum TokenType {
     LeftParen, RightParen,
     LeftBrace, RightBrace,
     LeftBracket, RightBracket,
 
     Comma,
-    Dot,
-    Plus,
-    Minus,
-    Star,
-    Slash,
+    Dot, DotDot,
+    Plus, PlusPlus, PlusEq,
+    Minus, MinusMinus, MinusEq,
+    Star, StarEq,
+    Slash, SlashEq,
+    Percent, PercentEq,
 
     Semicolon,
+    Colon,
+    QuestionDot, Elvis,
 
-    Bang, BangEq,
+    Bang, BangBang, BangEq,
     BitComplement,
     EQ, EqEq,
     Gt, Ge,
     Le, Lt,
-    BitAnd, And,
-    BitOr, Or,
-    XOR,
+    BitAnd, BitAndEq, And,
+    BitOr, BitOrEq, Or,
+    XOR, XorEq,
+    Shl, ShlEq,
+    Shr, ShrEq,
 
-    Identifier, String, Number,
+    Identifier, String, RawString, MultilineString, Interpolation, Number,
 
     True,
     False,
@@ -38,6 +44,7 @@ pub enum TokenType {
     Return,
     While,
     For,
+    In,
     Var,
     Val,
     Fun,
@@ -45,6 +52,7 @@ pub enum TokenType {
     Null,
     This,
     Super,
+    Print,
 
     Error,
     EOF,
@@ -61,44 +69,69 @@ impl From<TokenType> for usize {
             TokenType::RightBracket => {5}
             TokenType::Comma => {6}
             TokenType::Dot => {7}
-            TokenType::Plus => {8}
-            TokenType::Minus => {9}
-            TokenType::Star => {10}
-            TokenType::Slash => {11}
-            TokenType::Semicolon => {12}
-            TokenType::Bang => {13}
-            TokenType::BangEq => {14}
-            TokenType::BitComplement => {15}
-            TokenType::EQ => {16}
-            TokenType::EqEq => {17}
-            TokenType::Gt => {18}
-            TokenType::Ge => {19}
-            TokenType::Le => {20}
-            TokenType::Lt => {21}
-            TokenType::BitAnd => {22}
-            TokenType::And => {23}
-            TokenType::BitOr => {24}
-            TokenType::Or => {25}
-            TokenType::XOR => {26}
-            TokenType::Identifier => {27}
-            TokenType::String => {28}
-            TokenType::Number => {29}
-            TokenType::True => {30}
-            TokenType::False => {31}
-            TokenType::If => {32}
-            TokenType::Else => {33}
-            TokenType::Return => {34}
-            TokenType::While => {35}
-            TokenType::For => {36}
-            TokenType::Var => {37}
-            TokenType::Val => {38}
-            TokenType::Fun => {39}
-            TokenType::Class => {40}
-            TokenType::Null => {41}
-            TokenType::This => {42}
-            TokenType::Super => {43}
-            TokenType::Error => {44}
-            TokenType::EOF => {45}
+            TokenType::DotDot => {8}
+            TokenType::Plus => {9}
+            TokenType::PlusPlus => {10}
+            TokenType::PlusEq => {11}
+            TokenType::Minus => {12}
+            TokenType::MinusMinus => {13}
+            TokenType::MinusEq => {14}
+            TokenType::Star => {15}
+            TokenType::StarEq => {16}
+            TokenType::Slash => {17}
+            TokenType::SlashEq => {18}
+            TokenType::Percent => {19}
+            TokenType::PercentEq => {20}
+            TokenType::Semicolon => {21}
+            TokenType::Colon => {22}
+            TokenType::QuestionDot => {23}
+            TokenType::Elvis => {24}
+            TokenType::Bang => {25}
+            TokenType::BangBang => {26}
+            TokenType::BangEq => {27}
+            TokenType::BitComplement => {28}
+            TokenType::EQ => {29}
+            TokenType::EqEq => {30}
+            TokenType::Gt => {31}
+            TokenType::Ge => {32}
+            TokenType::Le => {33}
+            TokenType::Lt => {34}
+            TokenType::BitAnd => {35}
+            TokenType::BitAndEq => {36}
+            TokenType::And => {37}
+            TokenType::BitOr => {38}
+            TokenType::BitOrEq => {39}
+            TokenType::Or => {40}
+            TokenType::XOR => {41}
+            TokenType::XorEq => {42}
+            TokenType::Shl => {43}
+            TokenType::ShlEq => {44}
+            TokenType::Shr => {45}
+            TokenType::ShrEq => {46}
+            TokenType::Identifier => {47}
+            TokenType::String => {48}
+            TokenType::RawString => {49}
+            TokenType::MultilineString => {50}
+            TokenType::Interpolation => {51}
+            TokenType::Number => {52}
+            TokenType::True => {53}
+            TokenType::False => {54}
+            TokenType::If => {55}
+            TokenType::Else => {56}
+            TokenType::Return => {57}
+            TokenType::While => {58}
+            TokenType::For => {59}
+            TokenType::In => {60}
+            TokenType::Var => {61}
+            TokenType::Val => {62}
+            TokenType::Fun => {63}
+            TokenType::Class => {64}
+            TokenType::Null => {65}
+            TokenType::This => {66}
+            TokenType::Super => {67}
+            TokenType::Print => {68}
+            TokenType::Error => {69}
+            TokenType::EOF => {70}
         }
     }
 }
@@ -1 +1,133 @@
-pub type Value = f64;
\ No newline at end of file
+use std::fmt::{Display, Formatter};
+use super::bigint::*;
+use super::decimal::*;
+use super::object::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Null,
+    Bool(bool),
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(Decimal),
+    Float(u64),
+    String(*mut Obj),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Obj(*mut Obj),
+}
+
+impl Value {
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Null | Value::Bool(false))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Value::Int(_) => true,
+            Value::Obj(obj) => matches!(unsafe { &**obj }, Obj::BigInt(_)),
+            _ => false,
+        }
+    }
+
+    pub fn as_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Int(value) => Some(BigInt::from_i64(*value)),
+            Value::Obj(obj) => match unsafe { &**obj } {
+                Obj::BigInt(value) => Some(value.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn is_decimal(&self) -> bool {
+        match self {
+            Value::Obj(obj) => matches!(unsafe { &**obj }, Obj::Decimal(_)),
+            _ => false,
+        }
+    }
+
+    /// Integers widen to decimals exactly; floats do not, so mixing them must be explicit.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Obj(obj) => match unsafe { &**obj } {
+                Obj::Decimal(value) => Some(value.clone()),
+                _ => self.as_bigint().map(Decimal::from_bigint),
+            },
+            _ => self.as_bigint().map(Decimal::from_bigint),
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            Value::Int(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            Value::Obj(obj) => match unsafe { &*obj } {
+                Obj::BigInt(value) => Some(value.to_f64()),
+                Obj::Decimal(value) => Some(value.to_f64()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_string(&self) -> Option<&ObjString> {
+        match self {
+            Value::Obj(obj) => match unsafe { &**obj } {
+                Obj::String(string) => Some(string),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match *self {
+            Value::Null => Some(HashKey::Null),
+            Value::Bool(value) => Some(HashKey::Bool(value)),
+            Value::Int(value) => Some(HashKey::Int(value)),
+            // integral floats compare equal to ints, so they must hash like them too
+            Value::Float(value) => match BigInt::from_f64(value) {
+                Some(integral) => Some(integer_key(integral)),
+                None => Some(HashKey::Float(value.to_bits())),
+            },
+            Value::Obj(obj) => match unsafe { &*obj } {
+                Obj::String(_) => Some(HashKey::String(obj)),
+                Obj::BigInt(value) => Some(HashKey::BigInt(value.clone())),
+                Obj::Decimal(value) if value.is_integral() => Some(integer_key(value.to_bigint())),
+                // a decimal that is exactly some float must find that float's entry
+                Obj::Decimal(value) => match Decimal::from_f64_exact(value.to_f64()) {
+                    Some(exact) if exact == *value => Some(HashKey::Float(value.to_f64().to_bits())),
+                    _ => Some(HashKey::Decimal(value.clone())),
+                },
+                _ => None,
+            },
+        }
+    }
+}
+
+fn integer_key(value: BigInt) -> HashKey {
+    match value.to_i64() {
+        Some(small) => HashKey::Int(small),
+        None => HashKey::BigInt(value),
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Obj(obj) => unsafe { write!(f, "{}", **obj) },
+        }
+    }
+}
@@ -1,21 +1,162 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
+use std::mem::take;
+use std::ops::RangeInclusive;
+use std::ptr::null_mut;
+use super::bigint::*;
+use super::decimal::*;
 use super::chunk::*;
+use super::native;
 use super::opcode::*;
 use super::value::*;
 use super::debug::*;
 use super::scanner::*;
 use super::token::*;
 use super::parser::*;
+use super::memory::*;
+use super::object::*;
 use rawpointer::PointerExt;
 
-const STACK_LIMIT: usize = 256;
+const FRAMES_MAX: usize = 64;
+const STACK_LIMIT: usize = FRAMES_MAX * 256;
+const MAX_SHIFT: i64 = 1 << 16;
+
+macro_rules! bitwise_op {
+    ($vm:expr, $big:ident, $op:tt) => {
+        match ($vm.peek(1), $vm.peek(0)) {
+            (Value::Int(a), Value::Int(b)) => {
+                $vm.pop();
+                $vm.pop();
+                $vm.push(Value::Int(a $op b));
+            }
+            (a, b) => match (a.as_bigint(), b.as_bigint()) {
+                (Some(a), Some(b)) => {
+                    let result = $vm.heap.integer(a.$big(&b));
+                    $vm.pop();
+                    $vm.pop();
+                    $vm.push(result);
+                }
+                (Some(_), None) => {
+                    $vm.runtime_error(format!("Operands of bitwise operators must be integers, got {}.", b));
+                    return InterpreterResult::RuntimeError;
+                }
+                (None, _) => {
+                    $vm.runtime_error(format!("Operands of bitwise operators must be integers, got {}.", a));
+                    return InterpreterResult::RuntimeError;
+                }
+            },
+        }
+    };
+}
+
+macro_rules! arithmetic_op {
+    ($vm:expr, $checked:ident, $big:ident, $op:tt) => {
+        match ($vm.peek(1), $vm.peek(0)) {
+            (Value::Int(a), Value::Int(b)) => {
+                let result = match a.$checked(b) {
+                    Some(result) => Value::Int(result),
+                    None => $vm.heap.integer(BigInt::from_i64(a).$big(&BigInt::from_i64(b))),
+                };
+                $vm.pop();
+                $vm.pop();
+                $vm.push(result);
+            }
+            (a, b) if a.is_integer() && b.is_integer() => {
+                let result = $vm.heap.integer(a.as_bigint().unwrap().$big(&b.as_bigint().unwrap()));
+                $vm.pop();
+                $vm.pop();
+                $vm.push(result);
+            }
+            (a, b) => match (decimal_operands(a, b), a.as_float(), b.as_float()) {
+                (Some((a, b)), _, _) => {
+                    let result = Value::Obj($vm.heap.allocate(Obj::Decimal(a.$big(&b))));
+                    $vm.pop();
+                    $vm.pop();
+                    $vm.push(result);
+                }
+                (None, Some(_), Some(_)) if a.is_decimal() || b.is_decimal() => {
+                    $vm.runtime_error(String::from("Cannot mix decimal and float operands; convert with decimal() or float()."));
+                    return InterpreterResult::RuntimeError;
+                }
+                (None, Some(a), Some(b)) => {
+                    $vm.pop();
+                    $vm.pop();
+                    $vm.push(Value::Float(a $op b));
+                }
+                _ => {
+                    $vm.runtime_error(String::from("Operands must be numbers."));
+                    return InterpreterResult::RuntimeError;
+                }
+            },
+        }
+    };
+}
+
+macro_rules! comparison_op {
+    ($vm:expr, $op:tt) => {
+        match ($vm.peek(1), $vm.peek(0)) {
+            (Value::Int(a), Value::Int(b)) => {
+                $vm.pop();
+                $vm.pop();
+                $vm.push(Value::Bool(a $op b));
+            }
+            (a, b) if a.is_integer() && b.is_integer() => {
+                $vm.pop();
+                $vm.pop();
+                $vm.push(Value::Bool(a.as_bigint() $op b.as_bigint()));
+            }
+            (a, Value::Float(b)) if a.is_integer() => {
+                let ordering = compare_integer_float(a, b);
+                $vm.pop();
+                $vm.pop();
+                $vm.push(Value::Bool(ordering.is_some_and(|ordering| ordering $op Ordering::Equal)));
+            }
+            (Value::Float(a), b) if b.is_integer() => {
+                let ordering = compare_integer_float(b, a).map(Ordering::reverse);
+                $vm.pop();
+                $vm.pop();
+                $vm.push(Value::Bool(ordering.is_some_and(|ordering| ordering $op Ordering::Equal)));
+            }
+            (a, b) => match (decimal_comparands(a, b), a.as_float(), b.as_float()) {
+                (Some((a, b)), _, _) => {
+                    $vm.pop();
+                    $vm.pop();
+                    $vm.push(Value::Bool(a $op b));
+                }
+                (None, Some(a), Some(b)) => {
+                    $vm.pop();
+                    $vm.pop();
+                    $vm.push(Value::Bool(a $op b));
+                }
+                _ => {
+                    $vm.runtime_error(String::from("Operands must be numbers."));
+                    return InterpreterResult::RuntimeError;
+                }
+            },
+        }
+    };
+}
+
+pub struct CallFrame {
+    pub closure: *mut Obj,
+    pub ip: *mut u8,
+    pub slots: *mut Value,
+}
 
-#[derive(Clone, PartialEq)]
 pub struct VM {
-    pub chunk: Chunk,
+    pub frames: Vec<CallFrame>,
     pub ip: *mut u8,
     pub stack: [Value; STACK_LIMIT],
     pub stack_top: *mut Value,
+    pub heap: Heap,
+    pub globals: HashMap<*mut Obj, Value>,
+    pub immutable_globals: HashSet<String>,
+    /// Interned names of `immutable_globals`, checked by `OpSetGlobal` for assignments
+    /// compiled before the declaration.
+    pub immutable_names: HashSet<*mut Obj>,
+    pub open_upvalues: Vec<*mut Obj>,
+    pub init_string: *mut Obj,
 }
 
 pub enum InterpreterResult {
@@ -27,41 +168,85 @@ pub enum InterpreterResult {
 impl VM {
     pub fn new() -> VM {
         let mut vm = VM {
-            chunk: Chunk {
-                code: Vec::with_capacity(0),
-                lines: Vec::with_capacity(0),
-                constants: Vec::with_capacity(0),
-            },
-            ip: 0 as *mut u8,
-            stack: [0.0 as Value; STACK_LIMIT],
-            stack_top: 0 as *const Value as *mut Value,
+            frames: Vec::with_capacity(FRAMES_MAX),
+            ip: null_mut(),
+            stack: [Value::Null; STACK_LIMIT],
+            stack_top: null_mut(),
+            heap: Heap::default(),
+            globals: HashMap::new(),
+            immutable_globals: HashSet::new(),
+            immutable_names: HashSet::new(),
+            open_upvalues: Vec::new(),
+            init_string: null_mut(),
         };
-        vm.reset_stack();
+        if let Value::Obj(init_string) = vm.heap.copy_string("init") {
+            vm.init_string = init_string;
+        }
+        vm.define_native("clock", 0..=0, native::clock);
+        vm.define_native("round", 1..=2, native::round);
+        vm.define_native("floor", 1..=2, native::floor);
+        vm.define_native("ceil", 1..=2, native::ceil);
+        vm.define_native("trunc", 1..=2, native::trunc);
+        vm.define_native("decimal", 1..=1, native::decimal);
+        vm.define_native("float", 1..=1, native::float);
+        vm.define_native("int", 1..=1, native::int);
+        vm.define_native("str", 1..=1, native::str);
         vm
     }
 
     pub fn interpret(&mut self, source: String) -> InterpreterResult {
-        let mut chunk = Chunk::new();
-        self.compile(source, &mut chunk);
-        // self.compile(source);
-        InterpreterResult::Ok
+        self.interpret_source(source, false)
+    }
+
+    /// Like `interpret`, but prints the value of each top-level expression statement.
+    pub fn interpret_repl(&mut self, source: String) -> InterpreterResult {
+        self.interpret_source(source, true)
+    }
+
+    fn interpret_source(&mut self, source: String, echo_expressions: bool) -> InterpreterResult {
+        let function = match self.compile(source, echo_expressions) {
+            Some(function) => function,
+            None => return InterpreterResult::CompileError,
+        };
+
+        self.reset_stack();
+        let closure = self.heap.allocate(Obj::Closure(ObjClosure {
+            function,
+            upvalues: Vec::new(),
+        }));
+        self.push(Value::Obj(closure));
+        if !self.call(closure, 0) {
+            return InterpreterResult::RuntimeError;
+        }
 
-        // self.chunk = chunk;
-        // self.ip = self.chunk.code.as_mut_ptr();
-        //
-        // self.run()
+        self.run()
     }
 
-    pub fn compile(&mut self, source: String, chunk: &mut Chunk) -> bool {
+    pub fn compile(&mut self, source: String, echo_expressions: bool) -> Option<*mut Obj> {
         let mut scanner = Scanner::new(source);
-        let mut parser = Parser::new();
+        let mut parser = Parser::new(take(&mut self.heap), self.immutable_globals.clone());
+        parser.echo_expressions = echo_expressions;
 
         parser.advance(&mut scanner);
-        parser.expression(&mut scanner, chunk);
-        parser.consume(&mut scanner, TokenType::EOF, String::from("Expect end of expression."));
 
-        parser.end_compiler(chunk);
-        !parser.had_error
+        while !parser.match_token(&mut scanner, TokenType::EOF) {
+            parser.declaration(&mut scanner);
+        }
+
+        let function = parser.end_compiler();
+        self.heap = parser.heap;
+
+        if parser.had_error {
+            return None;
+        }
+
+        self.immutable_globals = parser.immutable_globals;
+        for name in &self.immutable_globals {
+            if let Value::Obj(name) = self.heap.copy_string(name) {
+                self.immutable_names.insert(name);
+            }
+        }
+        Some(self.heap.allocate(Obj::Function(function)))
     }
 
     // pub fn compile(&mut self, source: String) {
@@ -83,58 +268,785 @@ impl VM {
     //     }
     // }
 
-    // pub fn interpret(&mut self, chunk: Chunk) -> InterpreterResult {
-    //     self.chunk = chunk;
-    //     self.ip = self.chunk.code.as_mut_ptr();
-    //     self.run()
-    // }
-
     pub fn reset_stack(&mut self) {
         self.stack_top = self.stack.as_mut_ptr();
+        self.frames.clear();
+        self.open_upvalues.clear();
+    }
+
+    fn define_native(&mut self, name: &str, arity: RangeInclusive<usize>, function: NativeFn) {
+        let native = self.heap.allocate(Obj::Native(ObjNative {
+            name: String::from(name),
+            arity,
+            function,
+        }));
+
+        if let Value::Obj(name) = self.heap.copy_string(name) {
+            self.globals.insert(name, Value::Obj(native));
+            self.immutable_names.insert(name);
+        }
+        self.immutable_globals.insert(String::from(name));
     }
 
     pub fn run(&mut self) -> InterpreterResult {
         loop {
+            if cfg!(feature = "debug_trace_execution") {
+                print!("          ");
+                let mut slot = self.stack.as_ptr();
+                while slot < self.stack_top {
+                    unsafe {
+                        print!("[ {} ]", *slot);
+                        slot = slot.add(1);
+                    }
+                }
+                println!();
+                self.disassemble_current_instruction();
+            }
+
             unsafe {
                 let instruction = OpCode::from(*self.ip.post_inc());
                 match instruction {
                     OpCode::OpConstant => {
-                        let constant = self.chunk.constants[*self.ip.post_inc() as usize];
+                        let constant = self.read_constant();
                         self.push(constant);
                     }
-                    OpCode::OpAdd => {
-                        let b = self.pop();
-                        let a = self.pop();
-                        self.push(a + b);
+                    OpCode::OpNull => self.push(Value::Null),
+                    OpCode::OpTrue => self.push(Value::Bool(true)),
+                    OpCode::OpFalse => self.push(Value::Bool(false)),
+                    OpCode::OpGetLocal => {
+                        let slot = *self.ip.post_inc() as usize;
+                        self.push(*self.slot(slot));
                     }
-                    OpCode::OpSubtract => {
-                        let b = self.pop();
-                        let a = self.pop();
-                        self.push(a - b);
+                    OpCode::OpSetLocal => {
+                        let slot = *self.ip.post_inc() as usize;
+                        *self.slot(slot) = self.peek(0);
+                    }
+                    OpCode::OpDefineGlobal => {
+                        let name = self.read_name();
+                        let value = self.pop();
+                        self.globals.insert(name, value);
+                    }
+                    OpCode::OpGetGlobal => {
+                        let name = self.read_name();
+                        match self.globals.get(&name) {
+                            Some(value) => {
+                                let value = *value;
+                                self.push(value);
+                            }
+                            None => {
+                                self.runtime_error(format!("Undefined variable '{}'.", *name));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
                     }
-                    OpCode::OpMultiply => {
+                    OpCode::OpSetGlobal => {
+                        let name = self.read_name();
+                        if self.immutable_names.contains(&name) {
+                            self.runtime_error(format!("Cannot reassign val '{}'.", *name));
+                            return InterpreterResult::RuntimeError;
+                        }
+                        let value = self.peek(0);
+                        match self.globals.get_mut(&name) {
+                            Some(slot) => *slot = value,
+                            None => {
+                                self.runtime_error(format!("Undefined variable '{}'.", *name));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpGetUpvalue => {
+                        let slot = *self.ip.post_inc() as usize;
+                        let value = *as_upvalue(self.upvalue(slot)).location;
+                        self.push(value);
+                    }
+                    OpCode::OpSetUpvalue => {
+                        let slot = *self.ip.post_inc() as usize;
+                        *as_upvalue(self.upvalue(slot)).location = self.peek(0);
+                    }
+                    OpCode::OpGetProperty => {
+                        let name = self.read_name();
+                        let instance = match self.peek(0) {
+                            Value::Obj(obj) => match &*obj {
+                                Obj::Instance(instance) => instance,
+                                _ => {
+                                    self.runtime_error(String::from("Only instances have properties."));
+                                    return InterpreterResult::RuntimeError;
+                                }
+                            },
+                            _ => {
+                                self.runtime_error(String::from("Only instances have properties."));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        };
+
+                        if let Some(&value) = instance.fields.get(&name) {
+                            self.pop();
+                            self.push(value);
+                        } else if !self.bind_method(instance.class, name) {
+                            return InterpreterResult::RuntimeError;
+                        }
+                    }
+                    OpCode::OpSetProperty => {
+                        let name = self.read_name();
+                        match self.peek(1) {
+                            Value::Obj(obj) => match &mut *obj {
+                                Obj::Instance(instance) => {
+                                    let value = self.pop();
+                                    instance.fields.insert(name, value);
+                                    self.pop();
+                                    self.push(value);
+                                }
+                                _ => {
+                                    self.runtime_error(String::from("Only instances have fields."));
+                                    return InterpreterResult::RuntimeError;
+                                }
+                            },
+                            _ => {
+                                self.runtime_error(String::from("Only instances have fields."));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpGetSuper => {
+                        let name = self.read_name();
+                        let superclass = match self.pop() {
+                            Value::Obj(superclass) => superclass,
+                            _ => unreachable!("'super' always holds a class"),
+                        };
+
+                        if !self.bind_method(superclass, name) {
+                            return InterpreterResult::RuntimeError;
+                        }
+                    }
+                    OpCode::OpEqual => {
                         let b = self.pop();
                         let a = self.pop();
-                        self.push(a * b);
+                        self.push(Value::Bool(values_equal(a, b)));
                     }
+                    OpCode::OpGreater => comparison_op!(self, >),
+                    OpCode::OpGreaterEqual => comparison_op!(self, >=),
+                    OpCode::OpLess => comparison_op!(self, <),
+                    OpCode::OpLessEqual => comparison_op!(self, <=),
+                    OpCode::OpAdd => {
+                        if self.peek(0).as_string().is_some() && self.peek(1).as_string().is_some() {
+                            self.concatenate();
+                        } else if self.peek(0).as_float().is_some() && self.peek(1).as_float().is_some() {
+                            arithmetic_op!(self, checked_add, add, +);
+                        } else {
+                            self.runtime_error(String::from("Operands must be two numbers or two strings."));
+                            return InterpreterResult::RuntimeError;
+                        }
+                    }
+                    OpCode::OpSubtract => arithmetic_op!(self, checked_sub, sub, -),
+                    OpCode::OpMultiply => arithmetic_op!(self, checked_mul, mul, *),
                     OpCode::OpDivide => {
-                        let b = self.pop();
-                        let a = self.pop();
-                        self.push(a / b);
+                        if decimal_operands(self.peek(1), self.peek(0)).is_some_and(|(_, divisor)| divisor.is_zero()) {
+                            self.runtime_error(String::from("Decimal division by zero."));
+                            return InterpreterResult::RuntimeError;
+                        }
+                        if self.peek(1).is_integer() && self.peek(0) == Value::Int(0) {
+                            self.runtime_error(String::from("Integer division by zero."));
+                            return InterpreterResult::RuntimeError;
+                        }
+                        arithmetic_op!(self, checked_div, div, /);
+                    }
+                    OpCode::OpModulo => {
+                        if decimal_operands(self.peek(1), self.peek(0)).is_some_and(|(_, divisor)| divisor.is_zero()) {
+                            self.runtime_error(String::from("Decimal modulo by zero."));
+                            return InterpreterResult::RuntimeError;
+                        }
+                        if self.peek(1).is_integer() && self.peek(0) == Value::Int(0) {
+                            self.runtime_error(String::from("Integer modulo by zero."));
+                            return InterpreterResult::RuntimeError;
+                        }
+                        arithmetic_op!(self, checked_rem, rem, %);
+                    }
+                    OpCode::OpNot => {
+                        let value = self.pop();
+                        self.push(Value::Bool(value.is_falsey()));
+                    }
+                    OpCode::OpAssertNonNull => {
+                        if self.peek(0) == Value::Null {
+                            let line = self.chunk().get_line(self.ip as usize - self.chunk().code.as_ptr() as usize - 1);
+                            self.runtime_error(format!("Non-null assertion failed: expression on line {} is null.", line));
+                            return InterpreterResult::RuntimeError;
+                        }
                     }
                     OpCode::OpNegate => {
-                        *self.stack_top.sub(1) = -*self.stack_top.sub(1);
+                        match self.peek(0) {
+                            Value::Int(value) => {
+                                *self.stack_top.sub(1) = match value.checked_neg() {
+                                    Some(result) => Value::Int(result),
+                                    None => self.heap.integer(BigInt::from_i64(value).neg()),
+                                };
+                            }
+                            Value::Float(value) => *self.stack_top.sub(1) = Value::Float(-value),
+                            value if value.is_integer() => {
+                                *self.stack_top.sub(1) = self.heap.integer(value.as_bigint().unwrap().neg());
+                            }
+                            value if value.is_decimal() => {
+                                let result = value.as_decimal().unwrap().neg();
+                                *self.stack_top.sub(1) = Value::Obj(self.heap.allocate(Obj::Decimal(result)));
+                            }
+                            _ => {
+                                self.runtime_error(String::from("Operand must be a number."));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpBitAnd => bitwise_op!(self, bit_and, &),
+                    OpCode::OpBitOr => bitwise_op!(self, bit_or, |),
+                    OpCode::OpBitXor => bitwise_op!(self, bit_xor, ^),
+                    OpCode::OpBitNot => {
+                        match self.peek(0) {
+                            Value::Int(value) => *self.stack_top.sub(1) = Value::Int(!value),
+                            value if value.is_integer() => {
+                                *self.stack_top.sub(1) = self.heap.integer(value.as_bigint().unwrap().bit_not());
+                            }
+                            value => {
+                                self.runtime_error(format!("Operand of '~' must be an integer, got {}.", value));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpShiftLeft | OpCode::OpShiftRight => {
+                        if let Err(message) = self.shift(instruction == OpCode::OpShiftLeft) {
+                            self.runtime_error(message);
+                            return InterpreterResult::RuntimeError;
+                        }
+                    }
+                    OpCode::OpRange => {
+                        match (self.peek(1), self.peek(0)) {
+                            (Value::Int(start), Value::Int(end)) => {
+                                self.pop();
+                                self.pop();
+                                let range = self.heap.allocate(Obj::Range(ObjRange { start, end }));
+                                self.push(Value::Obj(range));
+                            }
+                            (Value::Int(_), bound) | (bound, _) => {
+                                self.runtime_error(format!("Range bounds must be 64-bit integers, got {}.", bound));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpBuildString => {
+                        let part_count = *self.ip.post_inc() as usize;
+                        let parts = std::slice::from_raw_parts(self.stack_top.sub(part_count), part_count);
+                        let result: String = parts.iter().map(|part| part.to_string()).collect();
+                        self.stack_top = self.stack_top.sub(part_count);
+
+                        let value = self.heap.take_string(result);
+                        self.push(value);
+                    }
+                    OpCode::OpBuildList => {
+                        let item_count = *self.ip.post_inc() as usize;
+                        let items = std::slice::from_raw_parts(self.stack_top.sub(item_count), item_count).to_vec();
+                        self.stack_top = self.stack_top.sub(item_count);
+
+                        let list = self.heap.allocate(Obj::List(ObjList { items }));
+                        self.push(Value::Obj(list));
+                    }
+                    OpCode::OpBuildMap => {
+                        let entry_count = *self.ip.post_inc() as usize;
+                        let mut map = ObjMap::default();
+
+                        for i in (0..entry_count).rev() {
+                            let key = self.peek(2 * i + 1);
+                            match key.hash_key() {
+                                Some(hash_key) => map.insert(hash_key, key, self.peek(2 * i)),
+                                None => {
+                                    self.runtime_error(unhashable_key(key));
+                                    return InterpreterResult::RuntimeError;
+                                }
+                            }
+                        }
+                        self.stack_top = self.stack_top.sub(2 * entry_count);
+
+                        let map = self.heap.allocate(Obj::Map(map));
+                        self.push(Value::Obj(map));
+                    }
+                    OpCode::OpIndexGet => {
+                        match self.index_get(self.peek(1), self.peek(0)) {
+                            Ok(value) => {
+                                self.pop();
+                                self.pop();
+                                self.push(value);
+                            }
+                            Err(message) => {
+                                self.runtime_error(message);
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpIndexSet => {
+                        let value = self.peek(0);
+                        match self.index_set(self.peek(2), self.peek(1), value) {
+                            Ok(()) => {
+                                self.stack_top = self.stack_top.sub(3);
+                                self.push(value);
+                            }
+                            Err(message) => {
+                                self.runtime_error(message);
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpPop => {
+                        self.pop();
+                    }
+                    OpCode::OpDup => {
+                        self.push(self.peek(0));
+                    }
+                    OpCode::OpDup2 => {
+                        self.push(self.peek(1));
+                        self.push(self.peek(1));
+                    }
+                    OpCode::OpBury => {
+                        let depth = *self.ip.post_inc() as usize;
+                        let value = self.peek(0);
+                        let slot = self.stack_top.sub(depth + 1);
+                        std::ptr::copy(slot, slot.add(1), depth);
+                        *slot = value;
+                    }
+                    OpCode::OpJump => {
+                        let offset = self.read_short();
+                        self.ip = self.ip.add(offset as usize);
+                    }
+                    OpCode::OpJumpIfFalse => {
+                        let offset = self.read_short();
+                        if self.peek(0).is_falsey() {
+                            self.ip = self.ip.add(offset as usize);
+                        }
+                    }
+                    OpCode::OpJumpIfNull => {
+                        let offset = self.read_short();
+                        if self.peek(0) == Value::Null {
+                            self.ip = self.ip.add(offset as usize);
+                        }
+                    }
+                    OpCode::OpPrint => {
+                        println!("{}", self.pop());
+                    }
+                    OpCode::OpLoop => {
+                        let offset = self.read_short();
+                        self.ip = self.ip.sub(offset as usize);
+                    }
+                    OpCode::OpForIter => {
+                        let slot = *self.ip.post_inc() as usize;
+                        let offset = self.read_short();
+
+                        let index = match *self.slot(slot + 1) {
+                            Value::Int(index) => index as usize,
+                            _ => unreachable!("for-in index slot always holds a number"),
+                        };
+
+                        match self.iterator_next(*self.slot(slot), index) {
+                            Ok(Some((element, next))) => {
+                                *self.slot(slot + 1) = Value::Int(next as i64);
+                                self.push(element);
+                            }
+                            Ok(None) => self.ip = self.ip.add(offset as usize),
+                            Err(message) => {
+                                self.runtime_error(message);
+                                return InterpreterResult::RuntimeError;
+                            }
+                        }
+                    }
+                    OpCode::OpCall => {
+                        let arg_count = *self.ip.post_inc() as usize;
+                        if !self.call_value(self.peek(arg_count), arg_count) {
+                            return InterpreterResult::RuntimeError;
+                        }
+                    }
+                    OpCode::OpClosure => {
+                        let function = self.read_name();
+                        let upvalue_count = as_function(function).upvalue_count;
+
+                        let mut upvalues = Vec::with_capacity(upvalue_count);
+                        for _ in 0..upvalue_count {
+                            let is_local = *self.ip.post_inc() == 1;
+                            let index = *self.ip.post_inc() as usize;
+
+                            if is_local {
+                                upvalues.push(self.capture_upvalue(self.slot(index)));
+                            } else {
+                                upvalues.push(self.upvalue(index));
+                            }
+                        }
+
+                        let closure = self.heap.allocate(Obj::Closure(ObjClosure { function, upvalues }));
+                        self.push(Value::Obj(closure));
+                    }
+                    OpCode::OpCloseUpvalue => {
+                        self.close_upvalues(self.stack_top.sub(1));
+                        self.pop();
+                    }
+                    OpCode::OpClass => {
+                        let name = self.read_name();
+                        let class = self.heap.allocate(Obj::Class(ObjClass {
+                            name: (*name).to_string(),
+                            methods: HashMap::new(),
+                        }));
+                        self.push(Value::Obj(class));
+                    }
+                    OpCode::OpInherit => {
+                        let superclass = match self.peek(1) {
+                            Value::Obj(obj) => match &*obj {
+                                Obj::Class(superclass) => superclass,
+                                _ => {
+                                    self.runtime_error(String::from("Superclass must be a class."));
+                                    return InterpreterResult::RuntimeError;
+                                }
+                            },
+                            _ => {
+                                self.runtime_error(String::from("Superclass must be a class."));
+                                return InterpreterResult::RuntimeError;
+                            }
+                        };
+
+                        if let Value::Obj(subclass) = self.peek(0) {
+                            as_class(subclass).methods.extend(superclass.methods.iter().map(|(&name, &method)| (name, method)));
+                        }
+                        self.pop();
+                    }
+                    OpCode::OpMethod => {
+                        let name = self.read_name();
+                        let method = self.peek(0);
+                        if let Value::Obj(class) = self.peek(1) {
+                            as_class(class).methods.insert(name, method);
+                        }
+                        self.pop();
                     }
                     OpCode::OpReturn => {
-                        println!("{:.3}", self.pop());
-                        return InterpreterResult::Ok
+                        let result = self.pop();
+                        let frame = self.frames.pop().unwrap();
+                        self.close_upvalues(frame.slots);
+
+                        if self.frames.is_empty() {
+                            self.pop();
+                            return InterpreterResult::Ok;
+                        }
+
+                        self.stack_top = frame.slots;
+                        self.push(result);
+                        self.ip = self.frames.last().unwrap().ip;
+                    }
+                    OpCode::Index(byte) => {
+                        self.runtime_error(format!("Unknown opcode {}.", byte));
+                        return InterpreterResult::RuntimeError;
                     }
-                    _ => {}
                 }
             }
         }
     }
 
+    fn shift(&mut self, left: bool) -> Result<(), String> {
+        let (value, amount) = (self.peek(1), self.peek(0));
+        let amount = match amount {
+            Value::Int(amount) if (0..=MAX_SHIFT).contains(&amount) => amount,
+            Value::Int(amount) => return Err(format!("Shift amount must be between 0 and {}, got {}.", MAX_SHIFT, amount)),
+            _ => return Err(format!("Operands of bitwise operators must be integers, got {}.", amount)),
+        };
+
+        let result = match value {
+            Value::Int(value) if left && amount < 64 && (value << amount) >> amount == value => Value::Int(value << amount),
+            Value::Int(value) if !left => Value::Int(value >> amount.min(63)),
+            value if value.is_integer() => {
+                let value = value.as_bigint().unwrap();
+                let shifted = if left { value.shl(amount as usize) } else { value.shr(amount as usize) };
+                self.heap.integer(shifted)
+            }
+            value => return Err(format!("Operands of bitwise operators must be integers, got {}.", value)),
+        };
+
+        self.pop();
+        self.pop();
+        self.push(result);
+        Ok(())
+    }
+
+    /// Returns the element at `index` and the index to resume from. Strings use byte
+    /// offsets so each step decodes one char instead of rescanning from the start.
+    fn iterator_next(&mut self, iterable: Value, index: usize) -> Result<Option<(Value, usize)>, String> {
+        if let Value::Obj(obj) = iterable {
+            match unsafe { &*obj } {
+                Obj::Range(range) => {
+                    // widened so ranges spanning the whole i64 domain cannot overflow
+                    let value = range.start as i128 + index as i128;
+                    return Ok(if value <= range.end as i128 {
+                        Some((Value::Int(value as i64), index + 1))
+                    } else {
+                        None
+                    });
+                }
+                Obj::String(string) => {
+                    return Ok(match string.value[index..].chars().next() {
+                        Some(c) => Some((self.heap.copy_string(c.encode_utf8(&mut [0; 4])), index + c.len_utf8())),
+                        None => None,
+                    });
+                }
+                Obj::List(list) => return Ok(list.items.get(index).map(|&item| (item, index + 1))),
+                Obj::Map(map) => return Ok(map.entries.get(index).map(|&(key, _)| (key, index + 1))),
+                _ => {}
+            }
+        }
+
+        Err(String::from("Can only iterate over ranges, strings, lists and maps."))
+    }
+
+    fn index_get(&self, target: Value, index: Value) -> Result<Value, String> {
+        if let Some(map) = as_map(target) {
+            return match index.hash_key() {
+                Some(key) => Ok(map.get(key).unwrap_or(Value::Null)),
+                None => Err(unhashable_key(index)),
+            };
+        }
+
+        self.list_slot(target, index).map(|slot| *slot)
+    }
+
+    fn index_set(&self, target: Value, index: Value, value: Value) -> Result<(), String> {
+        if let Some(map) = as_map(target) {
+            return match index.hash_key() {
+                Some(key) => {
+                    map.insert(key, index, value);
+                    Ok(())
+                }
+                None => Err(unhashable_key(index)),
+            };
+        }
+
+        self.list_slot(target, index).map(|slot| *slot = value)
+    }
+
+    fn list_slot<'a>(&self, target: Value, index: Value) -> Result<&'a mut Value, String> {
+        let list = match target {
+            Value::Obj(obj) => match unsafe { &mut *obj } {
+                Obj::List(list) => list,
+                _ => return Err(String::from("Can only index lists and maps.")),
+            },
+            _ => return Err(String::from("Can only index lists and maps.")),
+        };
+
+        let index = match index {
+            Value::Int(index) => index,
+            _ => return Err(format!("List index must be a 64-bit integer, got {}.", index)),
+        };
+
+        let length = list.items.len() as i64;
+        let position = if index < 0 { index + length } else { index };
+        if position < 0 || position >= length {
+            return Err(format!("List index {} out of bounds for length {}.", index, length));
+        }
+
+        Ok(&mut list.items[position as usize])
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> bool {
+        if let Value::Obj(obj) = callee {
+            match unsafe { &*obj } {
+                Obj::Closure(_) => return self.call(obj, arg_count),
+                Obj::BoundMethod(bound) => {
+                    unsafe {
+                        *self.stack_top.sub(arg_count + 1) = bound.receiver;
+                    }
+                    return self.call(bound.method, arg_count);
+                }
+                Obj::Class(class) => {
+                    let instance = self.heap.allocate(Obj::Instance(ObjInstance {
+                        class: obj,
+                        fields: HashMap::new(),
+                    }));
+                    unsafe {
+                        *self.stack_top.sub(arg_count + 1) = Value::Obj(instance);
+                    }
+
+                    if let Some(Value::Obj(initializer)) = class.methods.get(&self.init_string) {
+                        return self.call(*initializer, arg_count);
+                    } else if arg_count != 0 {
+                        self.runtime_error(format!("Expected 0 arguments but got {}.", arg_count));
+                        return false;
+                    }
+                    return true;
+                }
+                Obj::Native(native) => {
+                    if !native.arity.contains(&arg_count) {
+                        let expected = if native.arity.start() == native.arity.end() {
+                            native.arity.start().to_string()
+                        } else {
+                            format!("{} to {}", native.arity.start(), native.arity.end())
+                        };
+                        self.runtime_error(format!("Expected {} arguments but got {}.", expected, arg_count));
+                        return false;
+                    }
+
+                    let result = unsafe {
+                        let args = std::slice::from_raw_parts(self.stack_top.sub(arg_count), arg_count);
+                        (native.function)(&mut self.heap, args)
+                    };
+
+                    return match result {
+                        Ok(value) => {
+                            self.stack_top = unsafe { self.stack_top.sub(arg_count + 1) };
+                            self.push(value);
+                            true
+                        }
+                        Err(message) => {
+                            self.runtime_error(message);
+                            false
+                        }
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        self.runtime_error(String::from("Can only call functions and classes."));
+        false
+    }
+
+    fn bind_method(&mut self, class: *mut Obj, name: *mut Obj) -> bool {
+        let method = match as_class(class).methods.get(&name) {
+            Some(Value::Obj(method)) => *method,
+            _ => {
+                self.runtime_error(format!("Undefined property '{}'.", unsafe { &*name }));
+                return false;
+            }
+        };
+
+        let bound = self.heap.allocate(Obj::BoundMethod(ObjBoundMethod {
+            receiver: self.peek(0),
+            method,
+        }));
+        self.pop();
+        self.push(Value::Obj(bound));
+        true
+    }
+
+    fn call(&mut self, closure: *mut Obj, arg_count: usize) -> bool {
+        let function = as_closure(closure).function;
+        let arity = as_function(function).arity;
+
+        if arg_count != arity {
+            self.runtime_error(format!("Expected {} arguments but got {}.", arity, arg_count));
+            return false;
+        }
+
+        if self.frames.len() == FRAMES_MAX {
+            self.runtime_error(String::from("Stack overflow."));
+            return false;
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = self.ip;
+        }
+
+        self.ip = as_function(function).chunk.code.as_ptr() as *mut u8;
+        self.frames.push(CallFrame {
+            closure,
+            ip: self.ip,
+            slots: unsafe { self.stack_top.sub(arg_count + 1) },
+        });
+        true
+    }
+
+    fn chunk(&self) -> &Chunk {
+        &as_function(as_closure(self.frames.last().unwrap().closure).function).chunk
+    }
+
+    fn upvalue(&self, index: usize) -> *mut Obj {
+        as_closure(self.frames.last().unwrap().closure).upvalues[index]
+    }
+
+    fn capture_upvalue(&mut self, location: *mut Value) -> *mut Obj {
+        if let Some(&upvalue) = self.open_upvalues.iter().find(|&&upvalue| as_upvalue(upvalue).location == location) {
+            return upvalue;
+        }
+
+        let upvalue = self.heap.allocate(Obj::Upvalue(ObjUpvalue {
+            location,
+            closed: Value::Null,
+        }));
+        self.open_upvalues.push(upvalue);
+        upvalue
+    }
+
+    fn close_upvalues(&mut self, last: *mut Value) {
+        self.open_upvalues.retain(|&upvalue| {
+            let upvalue = as_upvalue(upvalue);
+            if upvalue.location < last {
+                return true;
+            }
+
+            unsafe {
+                upvalue.closed = *upvalue.location;
+            }
+            upvalue.location = &mut upvalue.closed;
+            false
+        });
+    }
+
+    fn slot(&self, index: usize) -> *mut Value {
+        unsafe {
+            self.frames.last().unwrap().slots.add(index)
+        }
+    }
+
+    fn read_constant(&mut self) -> Value {
+        unsafe {
+            let index = *self.ip.post_inc() as usize;
+            self.chunk().constants[index]
+        }
+    }
+
+    fn read_name(&mut self) -> *mut Obj {
+        match self.read_constant() {
+            Value::Obj(name) => name,
+            _ => unreachable!("name operands are always string constants"),
+        }
+    }
+
+    fn read_short(&mut self) -> u16 {
+        unsafe {
+            let high = *self.ip.post_inc() as u16;
+            let low = *self.ip.post_inc() as u16;
+            (high << 8) | low
+        }
+    }
+
+    fn concatenate(&mut self) {
+        let b = self.pop();
+        let a = self.pop();
+
+        let mut result = a.as_string().unwrap().value.clone();
+        result.push_str(&b.as_string().unwrap().value);
+
+        let value = self.heap.take_string(result);
+        self.push(value);
+    }
+
+    fn runtime_error(&mut self, message: String) {
+        eprintln!("{}", message);
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = self.ip;
+        }
+
+        for frame in self.frames.iter().rev() {
+            let function = as_function(as_closure(frame.closure).function);
+            let instruction = frame.ip as usize - function.chunk.code.as_ptr() as usize - 1;
+            let line = function.chunk.get_line(instruction);
+
+            if function.name.is_empty() {
+                eprintln!("[line {}] in script", line);
+            } else {
+                eprintln!("[line {}] in {}()", line, function.name);
+            }
+        }
+
+        self.reset_stack();
+    }
+
     pub fn push(&mut self, value: Value) {
         unsafe {
             *self.stack_top.post_inc() = value;
@@ -147,8 +1059,15 @@ impl VM {
         }
     }
 
-    pub fn disassemble_current_instruction(self) {
-        self.chunk.disassemble("VM DEBUG")
+    pub fn peek(&self, distance: usize) -> Value {
+        unsafe {
+            *self.stack_top.sub(distance + 1)
+        }
+    }
+
+    pub fn disassemble_current_instruction(&self) {
+        let offset = self.ip as usize - self.chunk().code.as_ptr() as usize;
+        disassemble_instruction(self.chunk(), offset);
     }
 }
 
@@ -156,4 +1075,106 @@ impl Debug for VM {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_list().entries(self.stack.iter()).finish()
     }
-}
\ No newline at end of file
+}
+
+fn values_equal(a: Value, b: Value) -> bool {
+    match (a, b) {
+        // exact, to agree with integral floats hashing as the i64 they equal
+        (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => {
+            b.fract() == 0.0 && b >= i64::MIN as f64 && b < i64::MAX as f64 && b as i64 == a
+        }
+        (Value::Float(float), other) | (other, Value::Float(float)) if other.is_integer() => {
+            BigInt::from_f64(float) == other.as_bigint()
+        }
+        _ if a.is_integer() && b.is_integer() => a.as_bigint() == b.as_bigint(),
+        _ if a.is_decimal() || b.is_decimal() => match decimal_comparands(a, b) {
+            Some((a, b)) => a == b,
+            None => false,
+        },
+        _ => a == b,
+    }
+}
+
+/// Orders an integer against a float without rounding either; `None` when the float is NaN.
+fn compare_integer_float(integer: Value, float: f64) -> Option<Ordering> {
+    if float.is_nan() {
+        return None;
+    }
+
+    let whole = float.trunc();
+    let ordering = match integer {
+        Value::Int(integer) if whole >= i64::MIN as f64 && whole < i64::MAX as f64 => integer.cmp(&(whole as i64)),
+        _ => match BigInt::from_f64(whole) {
+            Some(whole) => integer.as_bigint()?.cmp(&whole),
+            None if float > 0.0 => Ordering::Less,
+            None => Ordering::Greater,
+        },
+    };
+
+    // equal whole parts: a fractional remainder decides
+    Some(ordering.then(whole.partial_cmp(&float)?))
+}
+
+/// Both operands as decimals when at least one is a decimal and the other is an integer.
+fn decimal_operands(a: Value, b: Value) -> Option<(Decimal, Decimal)> {
+    if !a.is_decimal() && !b.is_decimal() {
+        return None;
+    }
+    Some((a.as_decimal()?, b.as_decimal()?))
+}
+
+/// Like `decimal_operands`, but also accepts floats at their exact binary value, so
+/// comparisons never round the decimal side.
+fn decimal_comparands(a: Value, b: Value) -> Option<(Decimal, Decimal)> {
+    if !a.is_decimal() && !b.is_decimal() {
+        return None;
+    }
+
+    let exact = |value: Value| match value {
+        Value::Float(value) => Decimal::from_f64_exact(value),
+        value => value.as_decimal(),
+    };
+    Some((exact(a)?, exact(b)?))
+}
+
+fn as_function<'a>(function: *mut Obj) -> &'a ObjFunction {
+    match unsafe { &*function } {
+        Obj::Function(function) => function,
+        _ => unreachable!("closures always wrap functions"),
+    }
+}
+
+fn as_closure<'a>(closure: *mut Obj) -> &'a ObjClosure {
+    match unsafe { &*closure } {
+        Obj::Closure(closure) => closure,
+        _ => unreachable!("call frames always hold closures"),
+    }
+}
+
+fn as_map<'a>(value: Value) -> Option<&'a mut ObjMap> {
+    match value {
+        Value::Obj(obj) => match unsafe { &mut *obj } {
+            Obj::Map(map) => Some(map),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn unhashable_key(key: Value) -> String {
+    format!("Map keys must be strings, numbers, booleans or null, got {}.", key)
+}
+
+fn as_class<'a>(class: *mut Obj) -> &'a mut ObjClass {
+    match unsafe { &mut *class } {
+        Obj::Class(class) => class,
+        _ => unreachable!("methods are only attached to classes"),
+    }
+}
+
+fn as_upvalue<'a>(upvalue: *mut Obj) -> &'a mut ObjUpvalue {
+    match unsafe { &mut *upvalue } {
+        Obj::Upvalue(upvalue) => upvalue,
+        _ => unreachable!("closures only capture upvalues"),
+    }
+}
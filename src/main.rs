@@ -35,7 +35,7 @@ fn repl(vm: &mut language::vm::VM) {
                 break;
             }
             _ => {
-                vm.interpret(line);
+                vm.interpret_repl(line);
             }
         }
     }